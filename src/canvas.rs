@@ -1,13 +1,38 @@
+pub mod changes;
+pub mod dither;
+pub mod layers;
+pub mod selection;
 pub mod shapes;
 pub mod tools;
+pub mod transform;
 use crate::{
     terminal::{Terminal, SIZE},
     util::{Color, Point, Size},
 };
+use layers::Layer;
 
+/// The largest possible canvas size in cells. This limits the memory a canvas can take up.
+pub const MAX_SIZE: Size = Size {
+    width: 2048,
+    height: 2048,
+};
+
+/// Points of the canvas are independent of the terminal: only the part of the canvas inside of the viewport is drawn.
+///
+/// A canvas consists of a stack of layers. Drawing happens on the current layer
+/// but what is shown is the composite of all visible layers.
 pub struct Canvas {
-    pub cells: Vec<Cell>, //[Cell; (SIZE::MAX as usize).pow(2)],
+    /// The layers from bottom to top. There is always at least one.
+    layers: Vec<Layer>,
+    /// The index of the layer that is drawn on.
+    layer: usize,
+    /// The width and height in cells.
+    size: Size,
+    /// The point of the canvas shown in the upper left corner of the terminal. Its y is always even.
+    viewport: Point,
     terminal: Terminal,
+    /// The changes made since they were last taken if they are recorded.
+    recording: Option<changes::Recording>,
 }
 
 #[derive(Clone, PartialEq, Debug, Default)]
@@ -21,111 +46,313 @@ pub struct Cell {
 }
 
 impl Canvas {
-    pub fn new() -> Self {
-        // (terminal.size.width, terminal.size.height);
+    /// Creates an empty canvas of the given size in cells, limited to [`MAX_SIZE`].
+    pub fn new(size: Size) -> Self {
+        let size = Self::limit_size(size);
         Self {
-            cells: vec![Default::default(); (SIZE::MAX as usize).pow(2)],
+            layers: vec![Layer::new(&size)],
+            layer: 0,
+            size,
+            viewport: Point::default(),
             terminal: Terminal::new(),
+            recording: None,
+        }
+    }
+
+    fn limit_size(size: Size) -> Size {
+        Size {
+            width: size.width.clamp(1, MAX_SIZE.width),
+            height: size.height.clamp(1, MAX_SIZE.height),
         }
     }
 
     pub fn resize_terminal(&mut self, size: Size) {
         self.terminal.size = size;
+        self.set_viewport(self.viewport);
     }
 
-    fn get_position(point: Point) -> usize {
-        point.x as usize + SIZE::MAX as usize * (point.y as usize / 2)
+    /// Returns the width and height of the canvas in cells.
+    pub fn size(&self) -> Size {
+        self.size.clone()
     }
 
-    pub fn get_cell(&self, point: Point) -> &Cell {
-        let position = Self::get_position(point);
+    /// Changes the size of the canvas, limited to [`MAX_SIZE`]. Cells that don't fit anymore are discarded.
+    pub fn resize(&mut self, size: Size) {
+        let size = Self::limit_size(size);
+        self.replace(|canvas| {
+            for layer in &mut canvas.layers {
+                let mut cells = vec![Cell::default(); size.width as usize * size.height as usize];
+                for row in 0..size.height.min(canvas.size.height) as usize {
+                    for x in 0..size.width.min(canvas.size.width) as usize {
+                        cells[x + row * size.width as usize] =
+                            layer.cells[x + row * canvas.size.width as usize].clone();
+                    }
+                }
+                layer.cells = cells;
+            }
+            canvas.size = size;
+            canvas.set_viewport(canvas.viewport);
+        });
+    }
 
-        self.cells
-            .get(position)
-            .unwrap_or_else(|| panic!("cell at {} is out of range", point))
+    pub fn viewport(&self) -> Point {
+        self.viewport
     }
 
-    pub fn write_character(&mut self, point: Point, character: char) {
-        let cell = self.get_mut_cell(point);
-        *cell = Cell {
-            upper_block: None,
-            lower_block: None,
-            character: Some(character),
-            ..*cell
+    /// Moves the viewport so that the given point is shown in the upper left corner of the terminal.
+    /// The viewport is kept from going past the canvas's right and bottom edges.
+    pub fn set_viewport(&mut self, point: Point) {
+        let max_x = self.size.width.saturating_sub(self.terminal.size.width);
+        let max_y = self.size.height.saturating_sub(self.terminal.size.height) as usize * 2;
+        self.viewport = Point {
+            x: point.x.min(max_x),
+            y: (point.y - point.y % 2).min(max_y as SIZE),
+        };
+    }
+
+    /// Converts a point of the terminal into the point of the canvas shown there.
+    /// The point's y is that of the cell's upper block.
+    pub fn canvas_point(&self, terminal_point: Point) -> Point {
+        Point {
+            x: terminal_point.x.saturating_add(self.viewport.x),
+            y: terminal_point
+                .y
+                .saturating_mul(2)
+                .saturating_add(self.viewport.y),
         }
     }
 
-    fn get_mut_cell(&mut self, point: Point) -> &mut Cell {
-        let position = Self::get_position(point);
+    /// Converts a point of the canvas into the point of the terminal it is shown at
+    /// or returns `None` if it's outside of the viewport.
+    fn terminal_point(&self, point: Point) -> Option<Point> {
+        if point.x < self.viewport.x || point.y < self.viewport.y {
+            return None;
+        }
+        let terminal_point = Point {
+            x: point.x - self.viewport.x,
+            y: (point.y - self.viewport.y) / 2,
+        };
+        if terminal_point.x < self.terminal.size.width
+            && terminal_point.y < self.terminal.size.height
+        {
+            Some(terminal_point)
+        } else {
+            None
+        }
+    }
 
-        self.cells
-            .get_mut(position)
-            .unwrap_or_else(|| panic!("cell at {} is out of range", point))
+    /// Returns whether the point is inside of the canvas.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x < self.size.width && point.y as usize / 2 < self.size.height as usize
     }
 
-    fn get_color(&self, point: Point) -> Color {
+    fn get_position(&self, point: Point) -> usize {
+        point.x as usize + self.size.width as usize * (point.y as usize / 2)
+    }
+
+    /// Returns the point of the upper block of the cell at the given position.
+    fn get_point(&self, position: usize) -> Point {
+        Point {
+            x: (position % self.size.width as usize) as SIZE,
+            y: (position / self.size.width as usize * 2) as SIZE,
+        }
+    }
+
+    /// Returns the cells of the current layer.
+    pub fn cells(&self) -> &[Cell] {
+        &self.layers[self.layer].cells
+    }
+
+    /// Returns the cell of the current layer at the given point.
+    pub fn get_cell(&self, point: Point) -> &Cell {
+        if !self.contains(point) {
+            panic!("cell at {} is out of range", point);
+        }
+        &self.cells()[self.get_position(point)]
+    }
+
+    pub fn write_character(&mut self, point: Point, character: char) {
+        self.set_cell(
+            point,
+            Cell {
+                character: Some(character),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Replaces the cell at the given point without drawing it.
+    pub fn set_cell(&mut self, point: Point, cell: Cell) {
+        let upper_point = Point {
+            y: point.y - point.y % 2,
+            ..point
+        };
+        let lower_point = Point {
+            y: upper_point.y + 1,
+            ..point
+        };
+        *self.get_mut_cell(point) = Cell {
+            upper_point,
+            lower_point,
+            ..cell
+        };
+    }
+
+    fn get_mut_cell(&mut self, point: Point) -> &mut Cell {
+        if !self.contains(point) {
+            panic!("cell at {} is out of range", point);
+        }
+        let position = self.get_position(point);
+        self.record_cell(self.layer, position);
+        &mut self.layers[self.layer].cells[position]
+    }
+
+    /// Returns the color of the block of the current layer at the given point or `None` if there is none.
+    pub fn get_block(&self, point: Point) -> Option<Color> {
         let cell = self.get_cell(point);
         if point.y % 2 == 0 {
-            if let Some(color) = cell.upper_block {
-                return color;
-            }
-        } else if let Some(color) = cell.lower_block {
-            return color;
+            cell.upper_block
+        } else {
+            cell.lower_block
         }
-        Color::default()
     }
 
+    fn get_color(&self, point: Point) -> Color {
+        self.get_block(point).unwrap_or_default()
+    }
+
+    /// Removes all layers and leaves a single empty one.
     pub fn clear(&mut self) {
-        self.cells.fill_with(Cell::default)
+        self.replace(|canvas| {
+            canvas.layers = vec![Layer::new(&canvas.size)];
+            canvas.layer = 0;
+        });
     }
 
-    /// Draws a half block. This method is exposed publicly in a higher level method [`Canvas::block`].
-    fn half_block(&mut self, point: Point, color: Color) {
-        let current_cell = self.get_cell(point);
-        if point.y % 2 == 0 {
-            if let Some(lower_block_color) = current_cell.lower_block {
-                self.terminal.set_background_color(lower_block_color);
-            }
-            self.terminal.write("▀");
-            let current_cell = self.get_mut_cell(point); // TODO: can a second `get` be avoided?
-            *current_cell = Cell {
-                upper_block: Some(color),
-                upper_point: point,
-                character: None,
-                ..*current_cell
-            };
-        } else {
-            if let Some(upper_block_color) = current_cell.upper_block {
-                self.terminal.set_background_color(upper_block_color);
+    /// Draws a half block on the current layer or removes it if the color is `None`.
+    /// This method is exposed publicly in a higher level method [`Canvas::block`].
+    ///
+    /// Points outside of the canvas are ignored and nothing is written to the terminal if the point is outside of the viewport.
+    fn half_block(&mut self, point: Point, color: Option<Color>) {
+        if !self.contains(point) {
+            return;
+        }
+        self.set_half_block(point, color);
+        self.redraw_cell(point);
+    }
+
+    /// Draws many blocks on the current layer at once, getting the color of each from the closure.
+    /// Every changed cell is drawn only once.
+    fn set_blocks(&mut self, points: &[Point], mut color: impl FnMut(Point) -> Color) {
+        let mut changed_cells = vec![false; self.cells().len()];
+        for &point in points {
+            if self.contains(point) {
+                self.set_half_block(point, Some(color(point)));
+                changed_cells[self.get_position(point)] = true;
             }
-            self.terminal.write("▄");
-            let current_cell = self.get_mut_cell(point); // TODO: can a second `get` be avoided?
-            *current_cell = Cell {
-                lower_block: Some(color),
-                lower_point: point,
-                character: None,
-                ..*current_cell
+        }
+        for (position, changed) in changed_cells.into_iter().enumerate() {
+            if changed {
+                self.redraw_cell(self.get_point(position));
             }
         }
     }
 
+    /// Replaces the half block at the given point on the current layer without drawing it.
+    fn set_half_block(&mut self, point: Point, color: Option<Color>) {
+        let current_cell = self.get_mut_cell(point);
+        if point.y % 2 == 0 {
+            current_cell.upper_block = color;
+            current_cell.upper_point = point;
+        } else {
+            current_cell.lower_block = color;
+            current_cell.lower_point = point;
+        }
+        current_cell.character = None;
+    }
+
+    /// Redraws the part of the canvas inside of the viewport.
+    /// The part of the terminal outside of the canvas is shaded.
     pub fn redraw(&mut self) {
-        for cell in &self.cells.clone() {
-            self.redraw_cell(cell);
+        for terminal_y in 0..self.terminal.size.height {
+            for terminal_x in 0..self.terminal.size.width {
+                let point = self.canvas_point(Point {
+                    x: terminal_x,
+                    y: terminal_y,
+                });
+                if self.contains(point) {
+                    self.redraw_cell(point);
+                } else {
+                    // The rest of the row is outside of the canvas as well
+                    self.terminal.set_cursor(Point {
+                        x: terminal_x,
+                        y: terminal_y,
+                    });
+                    self.terminal.set_foreground_color(Color::DarkGray);
+                    self.terminal
+                        .write(&"░".repeat((self.terminal.size.width - terminal_x) as usize));
+                    self.terminal.reset_colors();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Redraws the composite of the cell at the given point if it's inside of the viewport.
+    /// Points outside of the canvas are shaded.
+    pub fn redraw_cell(&mut self, point: Point) {
+        let terminal_point = match self.terminal_point(point) {
+            Some(terminal_point) => terminal_point,
+            None => return,
+        };
+        self.terminal.set_cursor(terminal_point);
+        if !self.contains(point) {
+            self.terminal.set_foreground_color(Color::DarkGray);
+            self.terminal.write("░");
+            self.terminal.reset_colors();
+            return;
+        }
+
+        let cell = self.composite_cell(point);
+        self.draw_cell(&cell);
+    }
+
+    /// Redraws the composite of the cell at the given point laid over the one of the other canvas
+    /// if it's inside of the viewport.
+    pub fn redraw_cell_over(&mut self, point: Point, below: &Canvas) {
+        if !self.contains(point) || !below.contains(point) {
+            return;
+        }
+        if let Some(terminal_point) = self.terminal_point(point) {
+            self.terminal.set_cursor(terminal_point);
+            let cell = layers::overlay(&self.composite_cell(point), &below.composite_cell(point));
+            self.draw_cell(&cell);
         }
     }
 
-    pub fn redraw_cell(&mut self, cell: &Cell) {
+    /// Draws the cell at the terminal cursor.
+    fn draw_cell(&mut self, cell: &Cell) {
         if let Some(character) = cell.character {
             self.terminal.write(&character.to_string());
-        } else {
-            if let Some(upper_block_color) = cell.upper_block {
-                self.block(cell.upper_point, upper_block_color);
+            return;
+        }
+        match (cell.upper_block, cell.lower_block) {
+            (Some(upper_block_color), lower_block) => {
+                self.terminal.set_foreground_color(upper_block_color);
+                if let Some(lower_block_color) = lower_block {
+                    self.terminal.set_background_color(lower_block_color);
+                }
+                self.terminal.write("▀");
             }
-            if let Some(lower_block_color) = cell.lower_block {
-                self.block(cell.lower_point, lower_block_color);
+            (None, Some(lower_block_color)) => {
+                self.terminal.set_foreground_color(lower_block_color);
+                self.terminal.write("▄");
+            }
+            (None, None) => {
+                self.terminal.write(" ");
             }
         }
+        self.terminal.reset_colors();
     }
 }
 
@@ -135,10 +362,13 @@ mod tests {
 
     #[test]
     fn test_get_point() {
-        let mut canvas = Canvas::new();
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
         let point = Point { x: 0, y: 0 };
         let color = Color::Red;
-        canvas.half_block(point, color);
+        canvas.half_block(point, Some(color));
         assert_eq!(canvas.get_color(point), color);
         assert_ne!(canvas.get_color(Point { x: 1, y: 0 }), color);
         assert_ne!(canvas.get_color(Point { x: 0, y: 1 }), color);
@@ -146,7 +376,7 @@ mod tests {
         canvas.clear();
         let point = Point { x: 0, y: 1 };
         let color = Color::Green;
-        canvas.half_block(point, color);
+        canvas.half_block(point, Some(color));
         assert_eq!(canvas.get_color(point), color);
         assert_ne!(canvas.get_color(Point { x: 0, y: 0 }), color);
         assert_ne!(canvas.get_color(Point { x: 0, y: 2 }), color);
@@ -154,9 +384,44 @@ mod tests {
         canvas.clear();
         let point = Point { x: 5, y: 3 };
         let color = Color::Blue;
-        canvas.half_block(point, color);
+        canvas.half_block(point, Some(color));
         assert_eq!(canvas.get_color(point), color);
         assert_ne!(canvas.get_color(Point { x: 5, y: 2 }), color);
         assert_ne!(canvas.get_color(Point { x: 5, y: 4 }), color);
     }
+
+    #[test]
+    fn test_resize() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 2,
+        });
+        canvas.half_block(Point { x: 2, y: 3 }, Some(Color::Red));
+        canvas.half_block(Point { x: 1, y: 0 }, Some(Color::Green));
+        // Outside of the canvas
+        canvas.half_block(Point { x: 3, y: 0 }, Some(Color::Blue));
+        canvas.half_block(Point { x: 0, y: 4 }, Some(Color::Blue));
+        assert_eq!(canvas.cells().len(), 6);
+
+        canvas.resize(Size {
+            width: 5,
+            height: 1,
+        });
+        assert_eq!(canvas.cells().len(), 5);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), Some(Color::Green));
+        assert!(!canvas.contains(Point { x: 2, y: 3 }));
+        assert!(canvas.contains(Point { x: 4, y: 1 }));
+
+        canvas.resize(MAX_SIZE);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), Some(Color::Green));
+        assert_eq!(canvas.get_block(Point { x: 2, y: 3 }), None);
+        assert!(canvas.contains(Point {
+            x: MAX_SIZE.width - 1,
+            y: MAX_SIZE.height * 2 - 1
+        }));
+        assert!(!canvas.contains(Point {
+            x: MAX_SIZE.width,
+            y: 0
+        }));
+    }
 }
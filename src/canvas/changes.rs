@@ -0,0 +1,293 @@
+//! Recording changes of the canvas so that they can be reverted and applied again.
+//!
+//! Changes of single cells are stored as the cell before and after the change
+//! so that reverting and applying them only takes as long as there are changed cells.
+
+use super::{layers::Layer, Canvas, Cell};
+use crate::util::{Point, Size};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug)]
+pub enum Change {
+    Cell {
+        layer: usize,
+        position: usize,
+        before: Cell,
+        after: Cell,
+    },
+    AddLayer {
+        index: usize,
+    },
+    RemoveLayer {
+        index: usize,
+        layer: Layer,
+    },
+    /// The layers at `index` and `index + 1` were swapped.
+    SwapLayers {
+        index: usize,
+    },
+    ToggleLayerVisibility {
+        index: usize,
+    },
+    /// The whole canvas was replaced, e.g. by clearing, resizing or importing.
+    Replace {
+        before: Box<Snapshot>,
+        after: Box<Snapshot>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    size: Size,
+    layers: Vec<Layer>,
+    layer: usize,
+}
+
+#[derive(Default)]
+pub struct Recording {
+    changes: Vec<Change>,
+    /// The cells changed since the last other change, by layer and position, with what they were before.
+    cells: BTreeMap<(usize, usize), Cell>,
+}
+
+impl Canvas {
+    /// Starts recording changes. Changes recorded so far are discarded.
+    pub fn record_changes(&mut self) {
+        self.recording = Some(Recording::default());
+    }
+
+    /// Returns the points of the cells changed since the changes were last taken and discards the changes.
+    pub fn take_changed_points(&mut self) -> Vec<Point> {
+        self.take_changed_cells()
+            .into_iter()
+            .map(|(point, _)| point)
+            .collect()
+    }
+
+    /// Returns the points of the cells changed since the changes were last taken with what the cells were before
+    /// and discards the changes.
+    pub fn take_changed_cells(&mut self) -> Vec<(Point, Cell)> {
+        self.take_changes()
+            .into_iter()
+            .filter_map(|change| match change {
+                Change::Cell {
+                    position, before, ..
+                } => Some((self.get_point(position), before)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the points of the cells changed since the changes were last taken without taking the changes.
+    pub fn changed_points(&self) -> Vec<Point> {
+        let recording = match &self.recording {
+            Some(recording) => recording,
+            None => return Vec::new(),
+        };
+        recording
+            .changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Cell { position, .. } => Some(*position),
+                _ => None,
+            })
+            .chain(recording.cells.keys().map(|(_, position)| *position))
+            .map(|position| self.get_point(position))
+            .collect()
+    }
+
+    /// Returns the changes recorded since the last call in the order they were made.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.flush_cell_changes();
+        match &mut self.recording {
+            Some(recording) => std::mem::take(&mut recording.changes),
+            None => Vec::new(),
+        }
+    }
+
+    /// Remembers what the cell was before it's changed.
+    pub(super) fn record_cell(&mut self, layer: usize, position: usize) {
+        let cell = &self.layers[layer].cells[position];
+        if let Some(recording) = &mut self.recording {
+            recording
+                .cells
+                .entry((layer, position))
+                .or_insert_with(|| cell.clone());
+        }
+    }
+
+    /// Records a change of the layers. Changes of cells have to be recorded before because they refer to layers by index.
+    pub(super) fn record(&mut self, change: Change) {
+        self.flush_cell_changes();
+        if let Some(recording) = &mut self.recording {
+            // Replacing the canvas several times in a row only needs the first and the last state
+            if let (
+                Change::Replace { after, .. },
+                Some(Change::Replace {
+                    after: last_after, ..
+                }),
+            ) = (&change, recording.changes.last_mut())
+            {
+                last_after.clone_from(after);
+                return;
+            }
+            recording.changes.push(change);
+        }
+    }
+
+    fn flush_cell_changes(&mut self) {
+        if let Some(recording) = &mut self.recording {
+            let cells = std::mem::take(&mut recording.cells);
+            for ((layer, position), before) in cells {
+                let after = &self.layers[layer].cells[position];
+                if before != *after {
+                    recording.changes.push(Change::Cell {
+                        layer,
+                        position,
+                        before,
+                        after: after.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Replaces the whole canvas using the closure and records it.
+    pub(super) fn replace(&mut self, replace: impl FnOnce(&mut Self)) {
+        if self.recording.is_none() {
+            replace(self);
+            return;
+        }
+        self.flush_cell_changes();
+        let before = self.snapshot();
+        replace(self);
+        let after = self.snapshot();
+        self.record(Change::Replace {
+            before: Box::new(before),
+            after: Box::new(after),
+        });
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            size: self.size.clone(),
+            layers: self.layers.clone(),
+            layer: self.layer,
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.size = snapshot.size.clone();
+        self.layers.clone_from(&snapshot.layers);
+        self.layer = snapshot.layer;
+        self.set_viewport(self.viewport);
+    }
+
+    /// Reverts the changes in reverse order and redraws what they changed.
+    pub fn revert_changes(&mut self, changes: &[Change]) {
+        for change in changes.iter().rev() {
+            match change {
+                Change::Cell {
+                    layer,
+                    position,
+                    before,
+                    ..
+                } => self.layers[*layer].cells[*position].clone_from(before),
+                Change::AddLayer { index } => {
+                    self.layers.remove(*index);
+                    self.layer = index.saturating_sub(1);
+                }
+                Change::RemoveLayer { index, layer } => {
+                    self.layers.insert(*index, layer.clone());
+                    self.layer = *index;
+                }
+                Change::SwapLayers { index } => self.swap_layers(*index),
+                Change::ToggleLayerVisibility { index } => self.toggle_visibility(*index),
+                Change::Replace { before, .. } => self.restore(before),
+            }
+        }
+        self.redraw_changes(changes);
+    }
+
+    /// Applies the changes again and redraws what they changed.
+    pub fn apply_changes(&mut self, changes: &[Change]) {
+        for change in changes {
+            match change {
+                Change::Cell {
+                    layer,
+                    position,
+                    after,
+                    ..
+                } => self.layers[*layer].cells[*position].clone_from(after),
+                Change::AddLayer { index } => {
+                    self.layers.insert(*index, Layer::new(&self.size));
+                    self.layer = *index;
+                }
+                Change::RemoveLayer { index, .. } => {
+                    self.layers.remove(*index);
+                    self.layer = index.saturating_sub(1);
+                }
+                Change::SwapLayers { index } => self.swap_layers(*index),
+                Change::ToggleLayerVisibility { index } => self.toggle_visibility(*index),
+                Change::Replace { after, .. } => self.restore(after),
+            }
+        }
+        self.redraw_changes(changes);
+    }
+
+    fn redraw_changes(&mut self, changes: &[Change]) {
+        if changes
+            .iter()
+            .all(|change| matches!(change, Change::Cell { .. }))
+        {
+            for change in changes {
+                if let Change::Cell { position, .. } = change {
+                    self.redraw_cell(self.get_point(*position));
+                }
+            }
+        } else {
+            self.redraw();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Color;
+
+    #[test]
+    fn test_revert_and_apply() {
+        let mut canvas = Canvas::new(Size {
+            width: 4,
+            height: 2,
+        });
+        canvas.block(Point { x: 0, y: 0 }, Color::Red);
+        canvas.record_changes();
+
+        canvas.block(Point { x: 1, y: 1 }, Color::Green);
+        canvas.block(Point { x: 1, y: 1 }, Color::Blue);
+        canvas.write_character(Point { x: 0, y: 0 }, 'a');
+        canvas.add_layer();
+        canvas.block(Point { x: 2, y: 2 }, Color::Yellow);
+        canvas.resize(Size {
+            width: 3,
+            height: 2,
+        });
+        let changes = canvas.take_changes();
+        assert!(canvas.take_changes().is_empty());
+        let layers = canvas.layers().to_vec();
+
+        canvas.revert_changes(&changes);
+        assert_eq!(canvas.layers().len(), 1);
+        assert_eq!(canvas.size().width, 4);
+        assert_eq!(canvas.get_block(Point { x: 0, y: 0 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), None);
+        assert_eq!(canvas.get_cell(Point { x: 0, y: 0 }).character, None);
+
+        canvas.apply_changes(&changes);
+        assert!(canvas.layers() == layers.as_slice());
+        assert_eq!(canvas.current_layer(), 1);
+        assert!(canvas.take_changes().is_empty());
+    }
+}
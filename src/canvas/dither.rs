@@ -0,0 +1,174 @@
+//! Dithering mixes colors by placing blocks of them next to each other.
+//! This makes up for colors that a terminal with fewer colors doesn't have.
+
+use super::Canvas;
+use crate::{
+    terminal::SIZE,
+    util::{Color, Point},
+};
+
+/// The 4x4 Bayer matrix for ordered dithering.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Returns the threshold of ordered dithering at the point, between 0 and 1.
+/// Where a mix of two colors is more of the second color than the threshold, the second color is used.
+pub fn bayer_threshold(point: Point) -> f64 {
+    (BAYER[point.y as usize % 4][point.x as usize % 4] as f64 + 0.5) / 16.0
+}
+
+/// Returns which of the two colors is used at the point to mix them with ordered dithering.
+/// The amount of the second color is from 0 to 1.
+pub fn mix(point: Point, (color, other_color): (Color, Color), amount: f64) -> Color {
+    if amount > bayer_threshold(point) {
+        other_color
+    } else {
+        color
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dithering {
+    /// Ordered dithering with a Bayer matrix. The pattern is regular and does not change when blocks change.
+    Bayer,
+    /// Error diffusion: the difference to the nearest color of each block is spread to the blocks next to it.
+    FloydSteinberg,
+}
+
+/// The colors that are dithered to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Palette {
+    FourBit,
+    EightBit,
+}
+
+impl Palette {
+    fn nearest_color(self, (r, g, b): (f64, f64, f64)) -> Color {
+        let (r, g, b) = (
+            r.round().clamp(0.0, 255.0) as u8,
+            g.round().clamp(0.0, 255.0) as u8,
+            b.round().clamp(0.0, 255.0) as u8,
+        );
+        match self {
+            Palette::FourBit => Color::nearest_four_bit_color(r, g, b),
+            Palette::EightBit => Color::nearest_ansi_color(r, g, b),
+        }
+    }
+
+    /// Returns by how much ordered dithering changes a color at most.
+    /// The fewer colors there are, the further apart they are.
+    fn spread(self) -> f64 {
+        match self {
+            Palette::FourBit => 128.0,
+            Palette::EightBit => 48.0,
+        }
+    }
+}
+
+impl Canvas {
+    /// Reduces the colors of the blocks of the current layer to the palette using dithering.
+    pub fn dither(&mut self, dithering: Dithering, palette: Palette) {
+        let width = self.size.width as usize;
+        let height = self.size.height as usize * 2;
+        let point = |index: usize| Point {
+            x: (index % width) as SIZE,
+            y: (index / width) as SIZE,
+        };
+        let mut colors = (0..width * height)
+            .map(|index| {
+                self.get_block(point(index)).map(|color| {
+                    let (r, g, b) = color.to_rgb();
+                    (r as f64, g as f64, b as f64)
+                })
+            })
+            .collect::<Vec<Option<(f64, f64, f64)>>>();
+
+        let mut dithered = Vec::<Option<Color>>::with_capacity(colors.len());
+        for index in 0..colors.len() {
+            let (r, g, b) = match colors[index] {
+                Some(color) => color,
+                None => {
+                    dithered.push(None);
+                    continue;
+                }
+            };
+            let color = match dithering {
+                Dithering::Bayer => {
+                    let offset = (bayer_threshold(point(index)) - 0.5) * palette.spread();
+                    palette.nearest_color((r + offset, g + offset, b + offset))
+                }
+                Dithering::FloydSteinberg => {
+                    let color = palette.nearest_color((r, g, b));
+                    let (new_r, new_g, new_b) = color.to_rgb();
+                    let error = (r - new_r as f64, g - new_g as f64, b - new_b as f64);
+                    let (x, y) = (index % width, index / width);
+                    let neighbours = [
+                        (x + 1 < width, index + 1, 7.0),
+                        (x > 0 && y + 1 < height, index + width - 1, 3.0),
+                        (y + 1 < height, index + width, 5.0),
+                        (x + 1 < width && y + 1 < height, index + width + 1, 1.0),
+                    ];
+                    for &(inside, neighbour, weight) in &neighbours {
+                        // Empty blocks don't take any of the error
+                        if inside {
+                            if let Some((r, g, b)) = &mut colors[neighbour] {
+                                *r += error.0 * weight / 16.0;
+                                *g += error.1 * weight / 16.0;
+                                *b += error.2 * weight / 16.0;
+                            }
+                        }
+                    }
+                    color
+                }
+            };
+            dithered.push(Some(color));
+        }
+
+        let points = (0..dithered.len())
+            .filter(|&index| dithered[index].is_some())
+            .map(point)
+            .collect::<Vec<Point>>();
+        self.set_blocks(&points, |point| {
+            dithered[point.x as usize + point.y as usize * width].expect("the block is dithered")
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_dither() {
+        let gray = Color::Rgb {
+            r: 64,
+            g: 64,
+            b: 64,
+        };
+        for &dithering in &[Dithering::Bayer, Dithering::FloydSteinberg] {
+            let mut canvas = Canvas::new(Size {
+                width: 4,
+                height: 2,
+            });
+            for y in 0..4 {
+                for x in 0..4 {
+                    canvas.block(Point { x, y }, gray);
+                }
+            }
+            canvas.dither(dithering, Palette::FourBit);
+
+            let mut black = 0;
+            for y in 0..4 {
+                for x in 0..4 {
+                    match canvas.get_block(Point { x, y }) {
+                        Some(Color::Black) => black += 1,
+                        Some(Color::DarkGray) => {}
+                        color => panic!("{:?} is not one of the nearest colors", color),
+                    }
+                }
+            }
+            // Half of the way from black to dark gray
+            assert_eq!(black, 8, "{:?}", dithering);
+        }
+    }
+}
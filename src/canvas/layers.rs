@@ -0,0 +1,250 @@
+use super::{changes::Change, Canvas, Cell};
+use crate::util::{Color, Point, Size};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layer {
+    pub cells: Vec<Cell>,
+    /// Hidden layers are not part of the composite.
+    pub visible: bool,
+}
+
+impl Layer {
+    /// Creates an empty, visible layer of the given size in cells.
+    pub fn new(size: &Size) -> Self {
+        Self {
+            cells: vec![Cell::default(); size.width as usize * size.height as usize],
+            visible: true,
+        }
+    }
+}
+
+/// Returns what is seen of the `upper` cell when it's laid over the `lower` cell.
+/// Blocks that are `None` are transparent.
+pub(super) fn overlay(upper: &Cell, lower: &Cell) -> Cell {
+    if upper.upper_block.is_none() && upper.lower_block.is_none() && upper.character.is_none() {
+        return lower.clone();
+    }
+    // A character uses up the entire cell so it can't be partially covered or cover something partially
+    if upper.character.is_some() || lower.character.is_some() {
+        return upper.clone();
+    }
+    Cell {
+        upper_block: upper.upper_block.or(lower.upper_block),
+        lower_block: upper.lower_block.or(lower.lower_block),
+        ..upper.clone()
+    }
+}
+
+impl Canvas {
+    /// Returns the layers from bottom to top.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Replaces all layers. The current layer is kept if it still exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no layers or a layer doesn't fit the canvas's size.
+    pub fn set_layers(&mut self, layers: Vec<Layer>) {
+        let cell_count = self.size.width as usize * self.size.height as usize;
+        assert!(!layers.is_empty(), "a canvas needs at least one layer");
+        assert!(
+            layers.iter().all(|layer| layer.cells.len() == cell_count),
+            "layer does not fit the canvas"
+        );
+        self.replace(|canvas| {
+            canvas.layers = layers;
+            canvas.layer = canvas.layer.min(canvas.layers.len() - 1);
+        });
+    }
+
+    /// Returns the index of the layer that is drawn on. The bottom layer is 0.
+    pub fn current_layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn select_layer(&mut self, index: usize) {
+        self.layer = index.min(self.layers.len() - 1);
+    }
+
+    /// Adds an empty layer above the current layer and selects it.
+    pub fn add_layer(&mut self) {
+        self.layer += 1;
+        self.layers.insert(self.layer, Layer::new(&self.size));
+        self.record(Change::AddLayer { index: self.layer });
+    }
+
+    /// Removes the current layer and selects the one below it.
+    /// If it's the only layer, it is replaced with an empty one.
+    pub fn remove_layer(&mut self) {
+        if self.layers.len() == 1 {
+            self.clear();
+            return;
+        }
+        let index = self.layer;
+        let layer = self.layers.remove(index);
+        self.layer = index.saturating_sub(1);
+        self.record(Change::RemoveLayer { index, layer });
+    }
+
+    pub fn toggle_layer_visibility(&mut self) {
+        let index = self.layer;
+        self.toggle_visibility(index);
+        self.record(Change::ToggleLayerVisibility { index });
+    }
+
+    /// Swaps the current layer with the one above it.
+    pub fn move_layer_up(&mut self) {
+        if self.layer + 1 < self.layers.len() {
+            let index = self.layer;
+            self.swap_layers(index);
+            self.record(Change::SwapLayers { index });
+        }
+    }
+
+    /// Swaps the current layer with the one below it.
+    pub fn move_layer_down(&mut self) {
+        if self.layer > 0 {
+            let index = self.layer - 1;
+            self.swap_layers(index);
+            self.record(Change::SwapLayers { index });
+        }
+    }
+
+    /// Swaps the layers at `index` and `index + 1`. The selection stays on the same layer.
+    pub(super) fn swap_layers(&mut self, index: usize) {
+        self.layers.swap(index, index + 1);
+        if self.layer == index {
+            self.layer += 1;
+        } else if self.layer == index + 1 {
+            self.layer -= 1;
+        }
+    }
+
+    /// Toggles the visibility of the layer and selects it.
+    pub(super) fn toggle_visibility(&mut self, index: usize) {
+        let layer = &mut self.layers[index];
+        layer.visible = !layer.visible;
+        self.layer = index;
+    }
+
+    /// Lays the current layer over the one below it, combines them into one and selects it.
+    /// The layer below keeps its visibility.
+    pub fn merge_down(&mut self) {
+        if self.layer == 0 {
+            return;
+        }
+        let upper = self.layer;
+        let lower = upper - 1;
+        // A hidden layer isn't seen so nothing of it is kept
+        if self.layers[upper].visible {
+            for position in 0..self.layers[upper].cells.len() {
+                let cell = overlay(
+                    &self.layers[upper].cells[position],
+                    &self.layers[lower].cells[position],
+                );
+                if cell != self.layers[lower].cells[position] {
+                    self.record_cell(lower, position);
+                    self.layers[lower].cells[position] = cell;
+                }
+            }
+        }
+        self.remove_layer();
+    }
+
+    /// Returns the block at the given point as it's seen with all visible layers laid over each other.
+    pub fn composite_block(&self, point: Point) -> Option<Color> {
+        let cell = self.composite_cell(point);
+        if point.y % 2 == 0 {
+            cell.upper_block
+        } else {
+            cell.lower_block
+        }
+    }
+
+    /// Returns the cell at the given point as it's seen with all visible layers laid over each other.
+    pub fn composite_cell(&self, point: Point) -> Cell {
+        if !self.contains(point) {
+            panic!("cell at {} is out of range", point);
+        }
+        let position = self.get_position(point);
+        let cell = self
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .fold(Cell::default(), |lower_cell, layer| {
+                overlay(&layer.cells[position], &lower_cell)
+            });
+        let upper_point = Point {
+            y: point.y - point.y % 2,
+            ..point
+        };
+        Cell {
+            upper_point,
+            lower_point: Point {
+                y: upper_point.y + 1,
+                ..point
+            },
+            ..cell
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let mut canvas = Canvas::new(Size {
+            width: 2,
+            height: 1,
+        });
+        let upper_point = Point { x: 0, y: 0 };
+        let lower_point = Point { x: 0, y: 1 };
+        canvas.block(upper_point, Color::Red);
+        canvas.block(lower_point, Color::Red);
+        canvas.add_layer();
+        canvas.block(upper_point, Color::Blue);
+        assert_eq!(canvas.current_layer(), 1);
+
+        let cell = canvas.composite_cell(upper_point);
+        assert_eq!(cell.upper_block, Some(Color::Blue));
+        assert_eq!(cell.lower_block, Some(Color::Red));
+
+        canvas.toggle_layer_visibility();
+        assert_eq!(
+            canvas.composite_cell(upper_point).upper_block,
+            Some(Color::Red)
+        );
+        canvas.toggle_layer_visibility();
+
+        canvas.move_layer_down();
+        assert_eq!(canvas.current_layer(), 0);
+        assert_eq!(
+            canvas.composite_cell(upper_point).upper_block,
+            Some(Color::Red)
+        );
+
+        canvas.select_layer(1);
+        canvas.write_character(Point { x: 1, y: 0 }, 'a');
+        canvas.merge_down();
+        assert_eq!(canvas.layers().len(), 1);
+        assert_eq!(canvas.get_block(upper_point), Some(Color::Red));
+        assert_eq!(canvas.get_block(lower_point), Some(Color::Red));
+        assert_eq!(canvas.get_cell(Point { x: 1, y: 0 }).character, Some('a'));
+
+        // A hidden layer doesn't change the layer below
+        canvas.add_layer();
+        canvas.block(lower_point, Color::Blue);
+        canvas.toggle_layer_visibility();
+        canvas.merge_down();
+        assert_eq!(canvas.layers().len(), 1);
+        assert_eq!(canvas.get_block(lower_point), Some(Color::Red));
+
+        canvas.remove_layer();
+        assert_eq!(canvas.layers().len(), 1);
+        assert_eq!(canvas.get_block(upper_point), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Copying, deleting and pasting rectangular areas of the current layer.
+//!
+//! Areas are given as their upper left and lower right point so they are precise to the half block.
+//! A character is copied if the upper half of its cell is inside of the area.
+
+use super::Canvas;
+use crate::{
+    terminal::SIZE,
+    util::{Color, Point},
+};
+
+/// A copied area.
+#[derive(Clone, Debug)]
+pub struct Clip {
+    /// The width in blocks.
+    pub width: SIZE,
+    /// The height in blocks.
+    pub height: SIZE,
+    /// The blocks row by row.
+    pub(super) blocks: Vec<Option<Color>>,
+    /// The characters with their points relative to the upper left corner.
+    pub(super) characters: Vec<(Point, char)>,
+}
+
+/// Returns the upper left and lower right corner of the area between two opposite corners.
+pub fn area(start: Point, end: Point) -> (Point, Point) {
+    (
+        Point {
+            x: start.x.min(end.x),
+            y: start.y.min(end.y),
+        },
+        Point {
+            x: start.x.max(end.x),
+            y: start.y.max(end.y),
+        },
+    )
+}
+
+/// Returns whether the point is inside of the area.
+pub fn area_contains((upper_left, lower_right): (Point, Point), point: Point) -> bool {
+    (upper_left.x..=lower_right.x).contains(&point.x)
+        && (upper_left.y..=lower_right.y).contains(&point.y)
+}
+
+impl Canvas {
+    /// Returns the points of the area that are on the canvas.
+    pub fn area_points(&self, (upper_left, lower_right): (Point, Point)) -> Vec<Point> {
+        let lower_right = Point {
+            x: lower_right.x.min(self.size.width - 1),
+            y: lower_right.y.min(self.size.height * 2 - 1),
+        };
+        (upper_left.y..=lower_right.y)
+            .flat_map(|y| (upper_left.x..=lower_right.x).map(move |x| Point { x, y }))
+            .collect()
+    }
+
+    /// Copies the area of the current layer. Parts outside of the canvas are empty.
+    pub fn copy(&self, (upper_left, lower_right): (Point, Point)) -> Clip {
+        let mut blocks = Vec::<Option<Color>>::new();
+        let mut characters = Vec::<(Point, char)>::new();
+        for y in upper_left.y..=lower_right.y {
+            for x in upper_left.x..=lower_right.x {
+                let point = Point { x, y };
+                if !self.contains(point) {
+                    blocks.push(None);
+                    continue;
+                }
+                blocks.push(self.get_block(point));
+                if let (true, Some(character)) = (y % 2 == 0, self.get_cell(point).character) {
+                    let point = Point {
+                        x: x - upper_left.x,
+                        y: y - upper_left.y,
+                    };
+                    characters.push((point, character));
+                }
+            }
+        }
+        Clip {
+            width: lower_right.x - upper_left.x + 1,
+            height: lower_right.y - upper_left.y + 1,
+            blocks,
+            characters,
+        }
+    }
+
+    /// Removes the blocks inside of the area of the current layer and the characters of the cells it overlaps.
+    pub fn delete(&mut self, (upper_left, lower_right): (Point, Point)) {
+        for y in upper_left.y..=lower_right.y {
+            for x in upper_left.x..=lower_right.x {
+                self.half_block(Point { x, y }, None);
+            }
+        }
+    }
+
+    /// Draws the clip on the current layer with its upper left corner at the given point.
+    /// Where there are no blocks in the clip, the canvas shows through.
+    pub fn paste(&mut self, clip: &Clip, point: Point) {
+        for (index, block) in clip.blocks.iter().enumerate() {
+            if let Some(color) = block {
+                let block_point = Point {
+                    x: point
+                        .x
+                        .saturating_add((index % clip.width as usize) as SIZE),
+                    y: point
+                        .y
+                        .saturating_add((index / clip.width as usize) as SIZE),
+                };
+                self.block(block_point, *color);
+            }
+        }
+        for (character_point, character) in &clip.characters {
+            let character_point = Point {
+                x: point.x.saturating_add(character_point.x),
+                y: point.y.saturating_add(character_point.y),
+            };
+            if self.contains(character_point) {
+                self.write_character(character_point, *character);
+                self.redraw_cell(character_point);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_copy_and_paste() {
+        let mut canvas = Canvas::new(Size {
+            width: 6,
+            height: 3,
+        });
+        canvas.block(Point { x: 1, y: 1 }, Color::Red);
+        canvas.block(Point { x: 2, y: 2 }, Color::Green);
+        canvas.write_character(Point { x: 2, y: 0 }, 'a');
+        let area = (Point { x: 1, y: 0 }, Point { x: 2, y: 2 });
+
+        let clip = canvas.copy(area);
+        assert_eq!((clip.width, clip.height), (2, 3));
+        canvas.delete(area);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), None);
+        assert_eq!(canvas.get_cell(Point { x: 2, y: 0 }).character, None);
+
+        // Moved by one half block
+        canvas.block(Point { x: 3, y: 1 }, Color::Blue);
+        canvas.paste(&clip, Point { x: 3, y: 1 });
+        assert_eq!(canvas.get_block(Point { x: 3, y: 2 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 3 }), Some(Color::Green));
+        // The clip is transparent where it has no blocks
+        assert_eq!(canvas.get_block(Point { x: 3, y: 1 }), Some(Color::Blue));
+        assert_eq!(canvas.get_cell(Point { x: 4, y: 0 }).character, Some('a'));
+    }
+}
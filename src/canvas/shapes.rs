@@ -1,48 +1,19 @@
+use super::{selection::area, Canvas};
 use crate::{
-    canvas::Canvas,
     terminal::SIZE,
     util::{Color, Point, Size},
 };
+use std::convert::TryFrom;
 
 impl Canvas {
-    // TODO: this should use `blocks`
-    pub fn circle(&mut self, point: Point, color: Color, radius: SIZE) {
-        let radius = radius as i32;
-        let center_x = point.x as i32;
-        let center_y = point.y as i32;
-
-        // Original: https://stackoverflow.com/a/59211338/15415674
-        // Changes were made
-        let radius_sqr = radius.pow(2);
-        let mut x = -radius;
-        while x < radius {
-            let hh = ((radius_sqr - x * x) as f64).sqrt() as i32;
-            let rx = center_x + x;
-            let ph = center_y + hh;
-            let mut y = center_y - hh;
-            while y < ph {
-                self.block(
-                    Point {
-                        x: rx as SIZE,
-                        y: y as SIZE,
-                    },
-                    color,
-                );
-                y += 1;
-            }
-            x += 1;
-        }
-    }
-
     pub fn hollow_rectangle(&mut self, point: Point, size: Size, color: Color) {
-        self.terminal.set_foreground_color(color);
         // -----
         //
         // -----
-        self.blocks_at(point, color, size.width);
-        self.blocks_at(
+        self.blocks(point, color, size.width);
+        self.blocks(
             Point {
-                y: point.y + size.height - 1,
+                y: point.y.saturating_add(size.height - 1),
                 ..point
             },
             color,
@@ -53,36 +24,299 @@ impl Canvas {
         // |   |
         // +---+
         for index in 1..size.height {
-            self.block_at(
+            self.block(
                 Point {
-                    y: point.y + index,
+                    y: point.y.saturating_add(index),
                     ..point
                 },
                 color,
             );
-            self.block_at(
+            self.block(
                 Point {
-                    x: point.x + size.width - 1,
-                    y: point.y + index,
+                    x: point.x.saturating_add(size.width - 1),
+                    y: point.y.saturating_add(index),
                 },
                 color,
             );
         }
-        self.terminal.reset_colors();
     }
 
     pub fn filled_rectangle(&mut self, point: Point, size: Size, color: Color) {
-        self.terminal.set_foreground_color(color);
         for y_index in 0..size.height {
-            self.blocks_at(
+            self.blocks(
                 Point {
-                    y: point.y + y_index,
+                    y: point.y.saturating_add(y_index),
                     ..point
                 },
                 color,
                 size.width,
             );
         }
-        self.terminal.reset_colors();
+    }
+
+    /// Draws a rectangle spanning the area between two opposite corners with an outline of the given thickness
+    /// and optionally fills the rest of it.
+    pub fn rectangle(
+        &mut self,
+        start: Point,
+        end: Point,
+        color: Color,
+        thickness: SIZE,
+        fill_color: Option<Color>,
+    ) {
+        let (upper_left, lower_right) = area(start, end);
+        let size = Size {
+            width: lower_right.x - upper_left.x + 1,
+            height: lower_right.y - upper_left.y + 1,
+        };
+        let inset = |inset: SIZE| {
+            if size.width > inset * 2 && size.height > inset * 2 {
+                Some((
+                    Point {
+                        x: upper_left.x + inset,
+                        y: upper_left.y + inset,
+                    },
+                    Size {
+                        width: size.width - inset * 2,
+                        height: size.height - inset * 2,
+                    },
+                ))
+            } else {
+                None
+            }
+        };
+
+        if let (Some(fill_color), Some((point, size))) = (fill_color, inset(thickness)) {
+            self.filled_rectangle(point, size, fill_color);
+        }
+        for index in 0..thickness {
+            if let Some((point, size)) = inset(index) {
+                self.hollow_rectangle(point, size, color);
+            }
+        }
+    }
+
+    /// Draws an ellipse fitting into the area between two opposite corners with an outline of the given thickness
+    /// and optionally fills the rest of it.
+    pub fn ellipse(
+        &mut self,
+        start: Point,
+        end: Point,
+        color: Color,
+        thickness: SIZE,
+        fill_color: Option<Color>,
+    ) {
+        let (upper_left, lower_right) = area(start, end);
+        let center_x = (upper_left.x as f64 + lower_right.x as f64) / 2.0;
+        let center_y = (upper_left.y as f64 + lower_right.y as f64) / 2.0;
+        // The radii reach the outer edges of the blocks
+        let radius_x = (lower_right.x - upper_left.x) as f64 / 2.0 + 0.5;
+        let radius_y = (lower_right.y - upper_left.y) as f64 / 2.0 + 0.5;
+        let inside = |x: SIZE, y: SIZE, inset: f64| {
+            let (radius_x, radius_y) = (radius_x - inset, radius_y - inset);
+            if radius_x <= 0.0 || radius_y <= 0.0 {
+                return false;
+            }
+            ((x as f64 - center_x) / radius_x).powi(2) + ((y as f64 - center_y) / radius_y).powi(2)
+                <= 1.0
+        };
+
+        for y in upper_left.y..=lower_right.y {
+            for x in upper_left.x..=lower_right.x {
+                if !inside(x, y, 0.0) {
+                    continue;
+                }
+                if !inside(x, y, thickness as f64) {
+                    self.block(Point { x, y }, color);
+                } else if let Some(fill_color) = fill_color {
+                    self.block(Point { x, y }, fill_color);
+                }
+            }
+        }
+    }
+
+    /// Draws lines of the given thickness from each of the points to the next one.
+    pub fn polyline(&mut self, points: &[Point], color: Color, thickness: SIZE) {
+        for pair in points.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            for point in self.line(start.x, start.y, end.x, end.y) {
+                let point = Point {
+                    x: point.x as SIZE,
+                    y: point.y as SIZE,
+                };
+                self.brush(point, color, thickness);
+            }
+        }
+        // The lines don't include their ends
+        if let Some(last_point) = points.last() {
+            self.brush(*last_point, color, thickness);
+        }
+    }
+
+    /// Draws a Bézier curve of the given thickness from the first to the last point that is bent towards the points in between.
+    pub fn bezier_curve(&mut self, points: &[Point], color: Color, thickness: SIZE) {
+        let points = points
+            .iter()
+            .map(|point| (point.x as f64, point.y as f64))
+            .collect::<Vec<(f64, f64)>>();
+        // The curve is at most as long as the lines between its points
+        let length = points
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+            .sum::<f64>();
+        let steps = (length.ceil() as usize).max(1);
+        let mut curve_points = Vec::<Point>::with_capacity(steps + 1);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            // De Casteljau's algorithm
+            let mut between = points.clone();
+            while between.len() > 1 {
+                between = between
+                    .windows(2)
+                    .map(|pair| {
+                        (
+                            pair[0].0 + (pair[1].0 - pair[0].0) * t,
+                            pair[0].1 + (pair[1].1 - pair[0].1) * t,
+                        )
+                    })
+                    .collect();
+            }
+            if let Some((x, y)) = between.first() {
+                let point = Point {
+                    x: x.round() as SIZE,
+                    y: y.round() as SIZE,
+                };
+                if curve_points.last() != Some(&point) {
+                    curve_points.push(point);
+                }
+            }
+        }
+        self.polyline(&curve_points, color, thickness);
+    }
+
+    /// Draws a closed polygon with an outline of the given thickness and optionally fills the inside of it.
+    pub fn polygon(
+        &mut self,
+        vertices: &[Point],
+        color: Color,
+        thickness: SIZE,
+        fill_color: Option<Color>,
+    ) {
+        let first_vertex = match vertices.first() {
+            Some(vertex) => *vertex,
+            None => return,
+        };
+        if let Some(fill_color) = fill_color {
+            let (min_y, max_y) = vertices.iter().fold((SIZE::MAX, 0), |(min, max), vertex| {
+                (min.min(vertex.y), max.max(vertex.y))
+            });
+            let sides = vertices
+                .iter()
+                .zip(vertices.iter().cycle().skip(1))
+                .collect::<Vec<(&Point, &Point)>>();
+            for y in min_y..=max_y {
+                // Where the row crosses the sides. Going through it from the left,
+                // the row alternates between being outside and inside of the polygon.
+                let mut crossings = sides
+                    .iter()
+                    .filter(|(start, end)| (start.y <= y) != (end.y <= y))
+                    .map(|(start, end)| {
+                        start.x as f64
+                            + (y as f64 - start.y as f64) * (end.x as f64 - start.x as f64)
+                                / (end.y as f64 - start.y as f64)
+                    })
+                    .collect::<Vec<f64>>();
+                crossings.sort_by(|a, b| a.partial_cmp(b).expect("crossings are numbers"));
+                for span in crossings.chunks_exact(2) {
+                    for x in span[0].ceil() as SIZE..=span[1].floor() as SIZE {
+                        self.block(Point { x, y }, fill_color);
+                    }
+                }
+            }
+        }
+        let mut points = vertices.to_vec();
+        points.push(first_vertex);
+        self.polyline(&points, color, thickness);
+    }
+}
+
+/// Returns the points of a filled circle.
+pub(super) fn circle_points(point: Point, radius: SIZE) -> Vec<Point> {
+    let mut points = Vec::new();
+    let radius = radius as i32;
+    let center_x = point.x as i32;
+    let center_y = point.y as i32;
+
+    // Original: https://stackoverflow.com/a/59211338/15415674
+    // Changes were made
+    let radius_sqr = radius.pow(2);
+    let mut x = -radius;
+    while x < radius {
+        let hh = ((radius_sqr - x * x) as f64).sqrt() as i32;
+        let rx = center_x + x;
+        let ph = center_y + hh;
+        let mut y = center_y - hh;
+        while y < ph {
+            if let (Ok(x), Ok(y)) = (SIZE::try_from(rx), SIZE::try_from(y)) {
+                points.push(Point { x, y });
+            }
+            y += 1;
+        }
+        x += 1;
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rectangle() {
+        let mut canvas = Canvas::new(Size {
+            width: 5,
+            height: 3,
+        });
+        canvas.rectangle(
+            Point { x: 4, y: 5 },
+            Point { x: 0, y: 0 },
+            Color::Red,
+            1,
+            Some(Color::Blue),
+        );
+        for y in 0..6 {
+            for x in 0..5 {
+                let expected = if x == 0 || x == 4 || y == 0 || y == 5 {
+                    Color::Red
+                } else {
+                    Color::Blue
+                };
+                assert_eq!(canvas.get_block(Point { x, y }), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ellipse() {
+        let mut canvas = Canvas::new(Size {
+            width: 9,
+            height: 5,
+        });
+        canvas.ellipse(
+            Point { x: 0, y: 0 },
+            Point { x: 8, y: 8 },
+            Color::Red,
+            1,
+            Some(Color::Blue),
+        );
+        // The corners are outside of the ellipse
+        assert_eq!(canvas.get_block(Point { x: 0, y: 0 }), None);
+        assert_eq!(canvas.get_block(Point { x: 8, y: 8 }), None);
+        // The edges are touched in the middle
+        assert_eq!(canvas.get_block(Point { x: 4, y: 0 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 0, y: 4 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 8, y: 4 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 8 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 4 }), Some(Color::Blue));
     }
 }
@@ -1,52 +1,34 @@
-use super::Canvas;
+use super::{dither, shapes::circle_points, Canvas};
 use crate::{
     terminal::SIZE,
-    util::{Color, Point, Size},
+    util::{Color, Point},
 };
 
-mod bucket;
+pub mod bucket;
+pub mod curve;
+pub mod gradient;
+pub mod pixel_perfect;
+pub mod polygon;
+pub mod spray;
+pub mod symmetry;
 
 impl Canvas {
-    /// Sets the terminal cursor accordingly and then draws a block.
-    pub fn block_at(&mut self, point: Point, color: Color) {
-        self.terminal.set_cursor(Point {
-            y: point.y / 2,
-            ..point
-        });
-        self.half_block(point, color);
-    }
-
-    /// Draws a block.
+    /// Draws a block on the current layer.
     pub fn block(&mut self, point: Point, color: Color) {
-        self.terminal.set_foreground_color(color);
-        self.block_at(point, color);
-        self.terminal.reset_colors();
+        self.half_block(point, Some(color));
     }
 
-    /// Sets the terminal cursor accordingly and then efficiently draws multiple blocks in a row.
-    pub fn blocks_at(&mut self, point: Point, color: Color, count: SIZE) {
-        self.terminal.set_cursor(Point {
-            y: point.y / 2,
-            ..point
-        });
+    /// Draws multiple blocks in a row on the current layer.
+    pub fn blocks(&mut self, point: Point, color: Color, count: SIZE) {
         for index in 0..count {
-            self.half_block(
-                Point {
-                    x: point.x + index,
-                    ..point
-                },
-                color,
-            );
+            let point = Point {
+                x: point.x.saturating_add(index),
+                ..point
+            };
+            self.half_block(point, Some(color));
         }
     }
 
-    /// Efficiently draws multiple blocks in a row.
-    pub fn blocks(&mut self, point: Point, color: Color, count: SIZE) {
-        self.terminal.set_foreground_color(color);
-        self.blocks_at(point, color, count);
-        self.terminal.reset_colors();
-    }
-
     pub fn line(
         &self,
         x1: SIZE,
@@ -59,145 +41,303 @@ impl Canvas {
     }
 
     pub fn brush(&mut self, point: Point, color: Color, size: SIZE) {
+        self.brush_half_blocks(point, |_| Some(color), size);
+    }
+
+    /// Draws in the same shape as [`Canvas::brush`] but mixes the two colors evenly with ordered dithering.
+    /// For a mirror image, `mirror` returns the points it's mirrored from so that the pattern is mirrored too.
+    pub fn dithered_brush(
+        &mut self,
+        point: Point,
+        colors: (Color, Color),
+        size: SIZE,
+        mirror: impl Fn(Point) -> Option<Point>,
+    ) {
+        self.brush_half_blocks(
+            point,
+            |point| Some(dither::mix(mirror(point).unwrap_or(point), colors, 0.5)),
+            size,
+        );
+    }
+
+    /// Removes blocks and characters of the current layer in the same shape as [`Canvas::brush`].
+    pub fn eraser(&mut self, point: Point, size: SIZE) {
+        self.brush_half_blocks(point, |_| None, size);
+    }
+
+    fn brush_half_blocks(
+        &mut self,
+        point: Point,
+        color: impl Fn(Point) -> Option<Color>,
+        size: SIZE,
+    ) {
         match size {
             1 => {
-                self.terminal.set_foreground_color(color);
-                self.block_at(point, color); // Middle dot
+                self.half_block(point, color(point)); // Middle dot
             }
             2 => {
-                self.terminal.set_foreground_color(color);
-                self.block_at(
-                    // Left dot
-                    Point {
-                        y: point.y - 1,
-                        ..point
-                    },
-                    color,
-                );
-                self.block_at(
-                    // Upper dot
-                    Point {
-                        x: point.x - 1,
-                        ..point
-                    },
-                    color,
-                );
-                self.block_at(point, color); // Middle dot
-                self.block_at(
-                    // Lower dot
+                // The middle dot and the dots around it
+                let mut points = vec![
+                    point,
                     Point {
-                        x: point.x + 1,
+                        x: point.x.saturating_add(1),
                         ..point
                     },
-                    color,
-                );
-                self.block_at(
-                    // Right dot
                     Point {
-                        y: point.y + 1,
+                        y: point.y.saturating_add(1),
                         ..point
                     },
-                    color,
-                );
+                ];
+                if let Some(y) = point.y.checked_sub(1) {
+                    points.push(Point { y, ..point });
+                }
+                if let Some(x) = point.x.checked_sub(1) {
+                    points.push(Point { x, ..point });
+                }
+                for point in points {
+                    self.half_block(point, color(point));
+                }
             }
             _ => {
-                self.circle(point, color, size - 1);
-                return; // `circle` has already reset the color
+                for point in circle_points(point, size - 1) {
+                    self.half_block(point, color(point));
+                }
             }
         }
-        self.terminal.reset_colors();
     }
 
     pub fn quill(&mut self, point: Point, color: Color, size: SIZE) {
-        self.terminal.set_foreground_color(color);
         for size in 0..=size {
             if size % 2 == 0 {
-                self.block_at(
+                self.block(
                     Point {
-                        y: point.y + size / 2,
-                        ..point
-                    },
-                    color,
-                );
-            } else {
-                self.block_at(
-                    Point {
-                        y: point.y - size / 2,
+                        y: point.y.saturating_add(size / 2),
                         ..point
                     },
                     color,
                 );
+            } else if let Some(y) = point.y.checked_sub(size / 2) {
+                self.block(Point { y, ..point }, color);
             }
         }
-        self.terminal.reset_colors();
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Default)]
 pub enum Tool {
+    #[default]
     Brush,
+    /// A brush that mixes the colors of both mouse buttons.
+    DitheredBrush,
     Quill,
     Rectangle,
-    Bucket,
+    Bucket(bucket::Fill),
     Text,
+    Line,
+    Ellipse,
+    Select,
+    Eraser,
+    Spray(spray::Spray),
+    Gradient(gradient::Gradient),
+    Polygon(polygon::Polygon),
+    Curve(curve::Curve),
 }
 
-impl Default for Tool {
-    fn default() -> Self {
-        Self::Brush
+impl Tool {
+    /// Returns whether the tool draws a shape from where the mouse button is pressed to where it's released
+    /// using [`Tool::draw_shape`].
+    pub fn draws_shape(&self) -> bool {
+        matches!(
+            self,
+            Tool::Line | Tool::Rectangle | Tool::Ellipse | Tool::Gradient(_)
+        )
     }
-}
 
-impl Tool {
-    pub fn draw(
+    /// Draws the shape from `start` to `end` with the tool size as the line thickness.
+    /// Rectangles and ellipses are filled with the fill color if there is one.
+    pub fn draw_shape(
         &self,
         canvas: &mut Canvas,
+        start: Point,
+        end: Point,
+        color: Color,
+        fill_color: Option<Color>,
+        size: SIZE,
+    ) {
+        match self {
+            Tool::Line => {
+                canvas.polyline(&[start, end], color, size);
+            }
+            Tool::Rectangle => {
+                canvas.rectangle(start, end, color, size, fill_color);
+            }
+            Tool::Ellipse => {
+                canvas.ellipse(start, end, color, size, fill_color);
+            }
+            Tool::Gradient(_) => {
+                // The gradient needs the area to fill so it's drawn using `Canvas::gradient`.
+                // This is the line that shows where it goes.
+                Tool::Line.draw_shape(canvas, start, end, color, None, 1);
+            }
+            _ => {}
+        }
+    }
+
+    /// Draws the blocks the tool would draw on at the point, e.g. to show them under the mouse.
+    pub fn draw_footprint(&self, canvas: &mut Canvas, point: Point, size: SIZE) {
+        let color = Color::default();
+        match self {
+            Tool::Brush
+            | Tool::DitheredBrush
+            | Tool::Eraser
+            | Tool::Rectangle
+            | Tool::Line
+            | Tool::Ellipse
+            | Tool::Polygon(_)
+            | Tool::Curve(_) => {
+                canvas.brush(point, color, size);
+            }
+            Tool::Quill => {
+                canvas.quill(point, color, size);
+            }
+            Tool::Spray(_) => {
+                // The brush's radius is one less than its size
+                canvas.brush(point, color, size.saturating_add(1));
+            }
+            Tool::Bucket(_) | Tool::Text | Tool::Select | Tool::Gradient(_) => {
+                canvas.block(point, color);
+            }
+        }
+    }
+
+    /// Uses the tool from the last point to the point.
+    /// One block thin strokes are drawn pixel-perfect if the stroke to continue is given.
+    ///
+    /// For a mirror image, `mirror` returns the points it's mirrored from
+    /// so that the randomness of the spray and the pattern of the dithered brush are mirrored too.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas,
         point: Point,
         last_point: Option<Point>,
-        color: Color,
+        colors: (Color, Color),
         tool_size: SIZE,
+        pixel_perfect: Option<&mut pixel_perfect::PixelPerfect>,
+        mirror: impl Fn(Point) -> Option<Point>,
     ) {
-        if let Some(last_point) = last_point {
-            if last_point == point {
-                self.r#use(canvas, point, color, tool_size);
-            } else {
-                for draw_point in canvas.line(last_point.x, last_point.y, point.x, point.y) {
-                    let draw_point = Point {
-                        x: draw_point.x as SIZE,
-                        y: draw_point.y as SIZE,
-                    };
-                    self.r#use(canvas, draw_point, color, tool_size);
+        let points = match last_point {
+            // The spray sprays only where the mouse is so that moving it slower sprays more
+            Some(last_point) if last_point != point && !matches!(self, Tool::Spray(_)) => canvas
+                .line(last_point.x, last_point.y, point.x, point.y)
+                .map(|point| Point {
+                    x: point.x as SIZE,
+                    y: point.y as SIZE,
+                })
+                .collect(),
+            _ => vec![point],
+        };
+        let pixel_perfect = pixel_perfect.filter(|_| {
+            tool_size == 1 && matches!(self, Tool::Brush | Tool::DitheredBrush | Tool::Eraser)
+        });
+        match pixel_perfect {
+            Some(pixel_perfect) => {
+                for point in points {
+                    pixel_perfect.draw(canvas, point, |canvas, point| {
+                        self.r#use(canvas, point, colors, tool_size, &mirror)
+                    });
+                }
+            }
+            None => {
+                for point in points {
+                    self.r#use(canvas, point, colors, tool_size, &mirror);
                 }
             }
-        } else {
-            self.r#use(canvas, point, color, tool_size);
         }
     }
 
-    fn r#use(&self, canvas: &mut Canvas, point: Point, color: Color, size: SIZE) {
+    /// Uses the tool at the point. The second color is the color of the other mouse button.
+    fn r#use(
+        &mut self,
+        canvas: &mut Canvas,
+        point: Point,
+        colors: (Color, Color),
+        size: SIZE,
+        mirror: impl Fn(Point) -> Option<Point>,
+    ) {
+        let color = colors.0;
         match self {
             Tool::Brush => {
                 canvas.brush(point, color, size);
             }
+            Tool::DitheredBrush => {
+                canvas.dithered_brush(point, colors, size, mirror);
+            }
             Tool::Quill => {
                 canvas.quill(point, color, size);
             }
-            Tool::Rectangle => {
-                canvas.hollow_rectangle(
-                    point,
-                    Size {
-                        width: size,
-                        height: size,
-                    },
-                    color,
-                );
+            Tool::Eraser => {
+                canvas.eraser(point, size);
+            }
+            Tool::Spray(spray) => {
+                canvas.spray(point, color, size, spray, mirror);
             }
-            Tool::Bucket => {
-                canvas.bucket(point, color);
+            Tool::Bucket(fill) => {
+                canvas.bucket(point, color, *fill);
             }
             Tool::Text => {
                 // This is handled in src/event.rs.
             }
+            Tool::Line | Tool::Rectangle | Tool::Ellipse | Tool::Gradient(_) => {
+                // These are drawn using `draw_shape`.
+            }
+            Tool::Select => {
+                // This is handled in src/event/selection.rs.
+            }
+            Tool::Polygon(_) => {
+                // Vertices are added in src/event.rs and the polygon is drawn once it's closed.
+            }
+            Tool::Curve(_) => {
+                // The handles are dragged in src/event.rs and the curve is drawn once it's committed.
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_eraser() {
+        let mut canvas = Canvas::new(Size {
+            width: 5,
+            height: 3,
+        });
+        canvas.brush(Point { x: 2, y: 2 }, Color::Black, 3);
+        canvas.write_character(Point { x: 1, y: 2 }, 'a');
+        assert_eq!(canvas.get_block(Point { x: 2, y: 2 }), Some(Color::Black));
+
+        canvas.eraser(Point { x: 2, y: 2 }, 3);
+        for cell in canvas.cells() {
+            assert_eq!(cell.upper_block, None);
+            assert_eq!(cell.lower_block, None);
+            assert_eq!(cell.character, None);
         }
     }
+
+    #[test]
+    fn test_rectangle_footprint() {
+        let size = Size {
+            width: 9,
+            height: 5,
+        };
+        let point = Point { x: 4, y: 4 };
+        let mut canvas = Canvas::new(size.clone());
+        Tool::Rectangle.draw_footprint(&mut canvas, point, 3);
+        let mut brush_canvas = Canvas::new(size);
+        Tool::Brush.draw_footprint(&mut brush_canvas, point, 3);
+        assert!(canvas.cells() == brush_canvas.cells());
+    }
 }
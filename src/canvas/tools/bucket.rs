@@ -1,57 +1,197 @@
 use crate::{canvas::Canvas, util::*};
 
+/// How the bucket decides which blocks to fill.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Fill {
+    /// How much each channel of an RGB color may differ from the color of the block clicked on.
+    /// Other colors have to match exactly.
+    pub tolerance: u8,
+    /// Whether blocks that only touch diagonally are connected.
+    pub diagonal: bool,
+    /// Whether all blocks of the color are filled, connected or not.
+    pub global: bool,
+}
+
+impl Fill {
+    fn matches(&self, color: Color, first_color: Color) -> bool {
+        match (color, first_color) {
+            (
+                Color::Rgb { r, g, b },
+                Color::Rgb {
+                    r: first_r,
+                    g: first_g,
+                    b: first_b,
+                },
+            ) => [(r, first_r), (g, first_g), (b, first_b)]
+                .iter()
+                .all(|(channel, first_channel)| channel.abs_diff(*first_channel) <= self.tolerance),
+            _ => color == first_color,
+        }
+    }
+}
+
 impl Canvas {
-    pub fn bucket(&mut self, point: Point, color: Color) {
-        let first_color = self.get_color(point);
-        if first_color == color {
+    /// Fills the area of same-colored blocks around the point.
+    pub fn bucket(&mut self, point: Point, color: Color, fill: Fill) {
+        if !self.contains(point) || (self.get_color(point) == color && fill.tolerance == 0) {
             return;
         }
-        let mut points = Vec::<Point>::new();
-        let mut new_points = Vec::<Point>::new();
-        new_points.push(point);
+        let points = self.bucket_area(point, fill);
+        self.set_blocks(&points, |_| color);
+    }
 
-        while !new_points.is_empty() {
-            let points_to_be_processed = new_points.clone();
-            points.append(&mut new_points);
-            for mut point in points_to_be_processed {
-                let current_point = point;
+    /// Returns the points of the area the bucket fills using a scanline fill.
+    ///
+    /// Each row of the area is taken from one edge to the other at once and
+    /// only the start of each run of blocks to take above and below it is remembered.
+    pub fn bucket_area(&self, point: Point, fill: Fill) -> Vec<Point> {
+        let mut area = Vec::<Point>::new();
+        if !self.contains(point) {
+            return area;
+        }
+        let first_color = self.get_color(point);
+        let width = self.size.width;
+        let height = self.size.height * 2;
+        let mut taken = vec![false; width as usize * height as usize];
+        let index = |point: Point| point.x as usize + point.y as usize * width as usize;
+        let fillable = |taken: &[bool], point: Point| {
+            !taken[index(point)] && fill.matches(self.get_color(point), first_color)
+        };
+        let mut take = |taken: &mut [bool], point: Point| {
+            taken[index(point)] = true;
+            area.push(point);
+        };
 
-                if point.x != 0 {
-                    point.x -= 1;
-                    self.process_point(point, first_color, color, &mut new_points);
-                    point = current_point;
+        if fill.global {
+            for y in 0..height {
+                for x in 0..width {
+                    let point = Point { x, y };
+                    if fillable(&taken, point) {
+                        take(&mut taken, point);
+                    }
                 }
+            }
+        }
 
-                if point.x != self.terminal.size.width {
-                    point.x += 1;
-                    self.process_point(point, first_color, color, &mut new_points);
-                    point = current_point;
-                }
+        let mut points = vec![point];
+        while let Some(Point { x, y }) = points.pop() {
+            if !fillable(&taken, Point { x, y }) {
+                continue;
+            }
+            let mut left = x;
+            while left > 0 && fillable(&taken, Point { x: left - 1, y }) {
+                left -= 1;
+            }
+            let mut right = x;
+            while right + 1 < width && fillable(&taken, Point { x: right + 1, y }) {
+                right += 1;
+            }
+            for x in left..=right {
+                take(&mut taken, Point { x, y });
+            }
 
-                if point.y != 0 {
-                    point.y -= 1;
-                    self.process_point(point, first_color, color, &mut new_points);
-                    point = current_point;
+            // Diagonal neighbours are next to the row's ends
+            let (left, right) = if fill.diagonal {
+                (left.saturating_sub(1), (right + 1).min(width - 1))
+            } else {
+                (left, right)
+            };
+            let above = y.checked_sub(1);
+            let below = Some(y + 1).filter(|&y| y < height);
+            for &y in [above, below].iter().flatten() {
+                let mut in_run = false;
+                for x in left..=right {
+                    let matches = fillable(&taken, Point { x, y });
+                    if matches && !in_run {
+                        points.push(Point { x, y });
+                    }
+                    in_run = matches;
                 }
+            }
+        }
+        area
+    }
+}
 
-                if point.y != self.terminal.size.height * 2 {
-                    point.y += 1;
-                    self.process_point(point, first_color, color, &mut new_points);
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        let mut canvas = Canvas::new(Size {
+            width: 5,
+            height: 3,
+        });
+        // A wall with a gap at the bottom
+        for y in 0..5 {
+            canvas.block(Point { x: 2, y }, Color::Red);
+        }
+        canvas.block(Point { x: 0, y: 2 }, Color::Red);
+
+        canvas.bucket(Point { x: 4, y: 0 }, Color::Blue, Fill::default());
+        for y in 0..6 {
+            for x in 0..5 {
+                let point = Point { x, y };
+                let expected = if x == 2 && y < 5 || point == (Point { x: 0, y: 2 }) {
+                    Color::Red
+                } else {
+                    Color::Blue
+                };
+                assert_eq!(canvas.get_block(point), Some(expected), "{}", point);
             }
         }
     }
 
-    fn process_point(
-        &mut self,
-        point: Point,
-        first_color: Color,
-        color: Color,
-        new_points: &mut Vec<Point>,
-    ) {
-        if self.get_color(point) == first_color {
-            self.block(point, color);
-            new_points.push(point);
+    #[test]
+    fn test_bucket_options() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 2,
+        });
+        let gray = |value| Color::Rgb {
+            r: value,
+            g: value,
+            b: value,
+        };
+        for y in 0..4 {
+            for x in 0..3 {
+                canvas.block(Point { x, y }, gray(100 + x as u8));
+            }
         }
+        // Only connected diagonally
+        canvas.block(Point { x: 1, y: 1 }, Color::Red);
+        canvas.block(Point { x: 0, y: 2 }, Color::Red);
+        canvas.block(Point { x: 2, y: 3 }, Color::Red);
+
+        let fill = Fill {
+            tolerance: 1,
+            ..Default::default()
+        };
+        canvas.bucket(Point { x: 0, y: 0 }, gray(50), fill);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), Some(gray(50)));
+        assert_eq!(canvas.get_block(Point { x: 2, y: 0 }), Some(gray(102)));
+
+        canvas.bucket(
+            Point { x: 1, y: 1 },
+            Color::Blue,
+            Fill {
+                diagonal: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(canvas.get_block(Point { x: 0, y: 2 }), Some(Color::Blue));
+        assert_eq!(canvas.get_block(Point { x: 2, y: 3 }), Some(Color::Red));
+
+        canvas.bucket(
+            Point { x: 0, y: 2 },
+            Color::Green,
+            Fill {
+                global: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), Some(Color::Green));
+        assert_eq!(canvas.get_block(Point { x: 2, y: 3 }), Some(Color::Red));
     }
 }
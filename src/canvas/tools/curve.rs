@@ -0,0 +1,118 @@
+//! The curve tool. A line is dragged from one end point to the other and gets a control point in the middle.
+//! Dragging the handles of the points bends the curve and clicking somewhere else adds a second control point.
+//! The curve is drawn once it's committed.
+
+use crate::{
+    canvas::Canvas,
+    terminal::SIZE,
+    util::{Color, Point},
+};
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Curve {
+    /// The end points with the control points between them.
+    points: Vec<Point>,
+    /// The index of the point being dragged.
+    dragging: Option<usize>,
+    color: Color,
+}
+
+impl Curve {
+    /// Starts a new curve at the point, which is dragged to where the curve ends.
+    pub fn start(&mut self, point: Point, color: Color) {
+        self.points = vec![point, point];
+        self.dragging = Some(1);
+        self.color = color;
+    }
+
+    /// Grabs the handle at the point or adds a second control point there.
+    /// Returns whether there was a handle or room for another control point.
+    pub fn grab(&mut self, point: Point) -> bool {
+        if self.points.is_empty() {
+            return false;
+        }
+        let handle = self
+            .points
+            .iter()
+            .position(|handle| handle.x.abs_diff(point.x) <= 1 && handle.y.abs_diff(point.y) <= 1);
+        if let Some(handle) = handle {
+            self.dragging = Some(handle);
+            true
+        } else if self.points.len() == 3 {
+            self.points.insert(2, point);
+            self.dragging = Some(2);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the grabbed handle to the point.
+    pub fn drag(&mut self, point: Point) {
+        if let Some(index) = self.dragging {
+            self.points[index] = point;
+        }
+    }
+
+    /// Lets go of the handle. The first line gets its control point in the middle.
+    pub fn release(&mut self) {
+        self.dragging = None;
+        if let [start, end] = self.points[..] {
+            let middle = Point {
+                x: ((start.x as u32 + end.x as u32) / 2) as SIZE,
+                y: ((start.y as u32 + end.y as u32) / 2) as SIZE,
+            };
+            self.points.insert(1, middle);
+        }
+    }
+
+    /// Returns the end points and the control points.
+    pub fn handles(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, thickness: SIZE) {
+        canvas.bezier_curve(&self.points, self.color, thickness);
+    }
+
+    /// Draws the curve and starts a new one.
+    pub fn commit(&mut self, canvas: &mut Canvas, thickness: SIZE) {
+        self.draw(canvas, thickness);
+        self.points.clear();
+        self.dragging = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_curve() {
+        let mut canvas = Canvas::new(Size {
+            width: 9,
+            height: 5,
+        });
+        let mut curve = Curve::default();
+        curve.start(Point { x: 0, y: 8 }, Color::Red);
+        curve.drag(Point { x: 8, y: 8 });
+        curve.release();
+        assert_eq!(curve.handles()[1], Point { x: 4, y: 8 });
+
+        // Bending the middle up
+        assert!(curve.grab(Point { x: 4, y: 7 }));
+        curve.drag(Point { x: 4, y: 0 });
+        curve.release();
+        assert!(curve.grab(Point { x: 6, y: 0 }));
+        curve.release();
+        assert!(!curve.grab(Point { x: 0, y: 0 }));
+
+        curve.commit(&mut canvas, 1);
+        assert!(curve.handles().is_empty());
+        assert_eq!(canvas.get_block(Point { x: 0, y: 8 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 8, y: 8 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 8 }), None);
+        assert!((0..8).any(|y| canvas.get_block(Point { x: 4, y }) == Some(Color::Red)));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    canvas::{dither, Canvas},
+    util::{Color, Point},
+};
+
+/// How the gradient tool fills.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Gradient {
+    /// Whether the colors change in circles around the start instead of along the line from the start to the end.
+    pub radial: bool,
+    /// Whether the whole canvas is filled instead of the area the bucket would fill.
+    pub whole_canvas: bool,
+}
+
+impl Gradient {
+    /// Returns how far the point is between the start and the end, from 0 to 1.
+    fn position(&self, start: Point, end: Point, point: Point) -> f64 {
+        let (dx, dy) = (end.x as f64 - start.x as f64, end.y as f64 - start.y as f64);
+        let (px, py) = (
+            point.x as f64 - start.x as f64,
+            point.y as f64 - start.y as f64,
+        );
+        let length_squared = dx * dx + dy * dy;
+        if length_squared == 0.0 {
+            return 0.0;
+        }
+        let position = if self.radial {
+            ((px * px + py * py) / length_squared).sqrt()
+        } else {
+            (px * dx + py * dy) / length_squared
+        };
+        position.clamp(0.0, 1.0)
+    }
+}
+
+/// Returns the color between the two colors at the position from 0 to 1, interpolated in RGB.
+fn interpolate(start_color: Color, end_color: Color, position: f64) -> Color {
+    let (start_r, start_g, start_b) = start_color.to_rgb();
+    let (end_r, end_g, end_b) = end_color.to_rgb();
+    let channel =
+        |start: u8, end: u8| (start as f64 + (end as f64 - start as f64) * position).round() as u8;
+    Color::Rgb {
+        r: channel(start_r, end_r),
+        g: channel(start_g, end_g),
+        b: channel(start_b, end_b),
+    }
+}
+
+impl Canvas {
+    /// Fills the points with a gradient from the first color at `start` to the second color at `end`.
+    ///
+    /// With truecolor, the colors are interpolated in RGB.
+    /// Otherwise only the two colors are used and mixed with ordered dithering.
+    pub fn gradient(
+        &mut self,
+        points: &[Point],
+        start: Point,
+        end: Point,
+        (start_color, end_color): (Color, Color),
+        gradient: Gradient,
+        truecolor: bool,
+    ) {
+        self.set_blocks(points, |point| {
+            let position = gradient.position(start, end, point);
+            if truecolor {
+                interpolate(start_color, end_color, position)
+            } else {
+                dither::mix(point, (start_color, end_color), position)
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_gradient() {
+        let mut canvas = Canvas::new(Size {
+            width: 5,
+            height: 1,
+        });
+        let points = (0..5).map(|x| Point { x, y: 0 }).collect::<Vec<Point>>();
+        let (start, end) = (Point { x: 0, y: 0 }, Point { x: 4, y: 0 });
+        let colors = (Color::Black, Color::White);
+
+        canvas.gradient(&points, start, end, colors, Gradient::default(), true);
+        assert_eq!(
+            canvas.get_block(start),
+            Some(Color::Rgb { r: 0, g: 0, b: 0 })
+        );
+        assert_eq!(
+            canvas.get_block(Point { x: 2, y: 0 }),
+            Some(Color::Rgb {
+                r: 128,
+                g: 128,
+                b: 128
+            })
+        );
+        assert_eq!(
+            canvas.get_block(end),
+            Some(Color::Rgb {
+                r: 255,
+                g: 255,
+                b: 255
+            })
+        );
+
+        canvas.gradient(&points, start, end, colors, Gradient::default(), false);
+        assert_eq!(canvas.get_block(start), Some(Color::Black));
+        assert_eq!(canvas.get_block(end), Some(Color::White));
+
+        let radial = Gradient {
+            radial: true,
+            ..Default::default()
+        };
+        canvas.gradient(&points, Point { x: 2, y: 0 }, end, colors, radial, true);
+        assert_eq!(canvas.get_block(start), canvas.get_block(end));
+    }
+}
@@ -0,0 +1,116 @@
+//! Pixel-perfect strokes. Lines between the points of a stroke that changes direction
+//! have a block in the corner where they meet, which makes the stroke look thicker there.
+//! These corners are removed again right after the block after them is drawn.
+
+use crate::{
+    canvas::{Canvas, Cell},
+    util::Point,
+};
+
+/// The blocks of one stroke that are needed to find corners.
+#[derive(Clone, Default)]
+pub struct PixelPerfect {
+    /// The last two blocks drawn with their cells before and after.
+    last_blocks: Vec<(Point, Cell, Cell)>,
+}
+
+/// Returns whether the second point is the corner of an L-shape between the first and the third point.
+fn is_corner(first: Point, second: Point, third: Point) -> bool {
+    let diagonal = first.x.abs_diff(third.x) == 1 && first.y.abs_diff(third.y) == 1;
+    diagonal
+        && ((second.x == first.x && second.y == third.y)
+            || (second.y == first.y && second.x == third.x))
+}
+
+impl PixelPerfect {
+    /// Draws the block at the point using the closure and puts back the block before it if it's a corner.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas,
+        point: Point,
+        draw: impl FnOnce(&mut Canvas, Point),
+    ) {
+        if !canvas.contains(point) {
+            return;
+        }
+        if let Some((last_point, ..)) = self.last_blocks.last() {
+            if *last_point == point {
+                return;
+            }
+        }
+        let before = canvas.get_cell(point).clone();
+        draw(canvas, point);
+        let after = canvas.get_cell(point).clone();
+        self.last_blocks.push((point, before, after));
+
+        if let [(first, ..), (second, ref second_before, ref second_after), (third, ..)] =
+            self.last_blocks[..]
+        {
+            if is_corner(first, second, third) {
+                if canvas.get_cell(second) == second_after {
+                    // This also puts back a character the block replaced
+                    canvas.set_cell(second, second_before.clone());
+                    canvas.redraw_cell(second);
+                } else {
+                    // The block after it is in the same cell
+                    let color = if second.y % 2 == 0 {
+                        second_before.upper_block
+                    } else {
+                        second_before.lower_block
+                    };
+                    canvas.half_block(second, color);
+                }
+                self.last_blocks.remove(1);
+            }
+        }
+        if self.last_blocks.len() > 2 {
+            self.last_blocks.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{Color, Size};
+
+    #[test]
+    fn test_pixel_perfect() {
+        let mut canvas = Canvas::new(Size {
+            width: 4,
+            height: 2,
+        });
+        canvas.block(Point { x: 1, y: 0 }, Color::Blue);
+        let mut pixel_perfect = PixelPerfect::default();
+        for &(x, y) in &[(0, 0), (1, 0), (1, 1), (2, 1), (2, 2), (2, 3)] {
+            pixel_perfect.draw(&mut canvas, Point { x, y }, |canvas, point| {
+                canvas.block(point, Color::Red)
+            });
+        }
+        // The corners are put back as they were
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), Some(Color::Blue));
+        assert_eq!(canvas.get_block(Point { x: 2, y: 1 }), None);
+        for &(x, y) in &[(0, 0), (1, 1), (2, 2), (2, 3)] {
+            assert_eq!(canvas.get_block(Point { x, y }), Some(Color::Red));
+        }
+    }
+
+    #[test]
+    fn test_pixel_perfect_character() {
+        let mut canvas = Canvas::new(Size {
+            width: 4,
+            height: 2,
+        });
+        canvas.write_character(Point { x: 1, y: 0 }, 'a');
+        let mut pixel_perfect = PixelPerfect::default();
+        for &(x, y) in &[(0, 1), (1, 1), (1, 2)] {
+            pixel_perfect.draw(&mut canvas, Point { x, y }, |canvas, point| {
+                canvas.block(point, Color::Red)
+            });
+        }
+        // The corner's cell gets its character back
+        assert_eq!(canvas.get_cell(Point { x: 1, y: 0 }).character, Some('a'));
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), None);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 2 }), Some(Color::Red));
+    }
+}
@@ -0,0 +1,76 @@
+//! The polygon tool. Each click adds a vertex and the sides between them are shown
+//! until the polygon is closed, which is when it's drawn.
+
+use crate::{
+    canvas::Canvas,
+    terminal::SIZE,
+    util::{Color, Point},
+};
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+    /// The colors of the outline and the fill, given by the mouse button the first vertex was added with.
+    colors: (Color, Color),
+}
+
+impl Polygon {
+    /// Adds a vertex and returns whether the polygon should be closed,
+    /// which is when the vertex is at most one block away from the last one, e.g. when double-clicking.
+    pub fn add_vertex(&mut self, point: Point, colors: (Color, Color)) -> bool {
+        if self.vertices.is_empty() {
+            self.colors = colors;
+        }
+        if let Some(last) = self.vertices.last() {
+            if last.x.abs_diff(point.x) <= 1 && last.y.abs_diff(point.y) <= 1 {
+                return true;
+            }
+        }
+        self.vertices.push(point);
+        false
+    }
+
+    /// Draws the sides between the vertices so far and one from the last vertex to the point if there is one.
+    pub fn draw_sides(&self, canvas: &mut Canvas, point: Option<Point>, thickness: SIZE) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let mut points = self.vertices.clone();
+        points.extend(point);
+        canvas.polyline(&points, self.colors.0, thickness);
+    }
+
+    /// Draws the closed polygon, optionally filled with the other color, and starts a new one.
+    pub fn close(&mut self, canvas: &mut Canvas, fill: bool, thickness: SIZE) {
+        let vertices = std::mem::take(&mut self.vertices);
+        let fill_color = Some(self.colors.1).filter(|_| fill);
+        canvas.polygon(&vertices, self.colors.0, thickness, fill_color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_polygon() {
+        let mut canvas = Canvas::new(Size {
+            width: 9,
+            height: 5,
+        });
+        let mut polygon = Polygon::default();
+        let colors = (Color::Red, Color::Blue);
+        for &(x, y) in &[(0, 0), (8, 0), (8, 8), (0, 8)] {
+            assert!(!polygon.add_vertex(Point { x, y }, colors));
+        }
+        // The second click of a double-click can be in the other half of the cell
+        assert!(polygon.add_vertex(Point { x: 0, y: 9 }, colors));
+        polygon.close(&mut canvas, true, 1);
+
+        assert_eq!(canvas.get_block(Point { x: 0, y: 4 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 8 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 4, y: 4 }), Some(Color::Blue));
+        assert!(polygon.vertices.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    canvas::Canvas,
+    terminal::SIZE,
+    util::{Color, Point, Random},
+};
+
+/// The spray scatters blocks randomly. The random numbers come from a seed so that spraying can be reproduced.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Spray {
+    /// The percentage of the blocks within the radius that are sprayed each time.
+    pub density: u8,
+    random: Random,
+}
+
+impl Spray {
+    pub fn new(density: u8, seed: u64) -> Self {
+        Self {
+            density,
+            random: Random::new(seed),
+        }
+    }
+}
+
+impl Canvas {
+    /// Draws blocks at random points within the radius around the point.
+    /// At least one block is drawn.
+    ///
+    /// For a mirror image, `mirror` returns the point it's mirrored from and the blocks are the mirror images
+    /// of the blocks sprayed around that point.
+    pub fn spray(
+        &mut self,
+        point: Point,
+        color: Color,
+        radius: SIZE,
+        spray: &mut Spray,
+        mirror: impl Fn(Point) -> Option<Point>,
+    ) {
+        let point = mirror(point).unwrap_or(point);
+        let radius = radius as i64;
+        let area = std::f64::consts::PI * (radius * radius) as f64;
+        let count = (area * spray.density.min(100) as f64 / 100.0)
+            .round()
+            .max(1.0) as usize;
+        for _ in 0..count {
+            // Random points of the square around the circle are tried until one is inside of it
+            let (x, y) = loop {
+                let x = spray.random.below(radius as u64 * 2 + 1) as i64 - radius;
+                let y = spray.random.below(radius as u64 * 2 + 1) as i64 - radius;
+                if x * x + y * y <= radius * radius {
+                    break (point.x as i64 + x, point.y as i64 + y);
+                }
+            };
+            if (0..=SIZE::MAX as i64).contains(&x) && (0..=SIZE::MAX as i64).contains(&y) {
+                let point = Point {
+                    x: x as SIZE,
+                    y: y as SIZE,
+                };
+                if let Some(point) = mirror(point) {
+                    self.block(point, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Size;
+
+    #[test]
+    fn test_spray() {
+        let spray = |seed| {
+            let mut canvas = Canvas::new(Size {
+                width: 20,
+                height: 10,
+            });
+            canvas.spray(
+                Point { x: 10, y: 10 },
+                Color::Red,
+                5,
+                &mut Spray::new(30, seed),
+                Some,
+            );
+            canvas.cells().to_vec()
+        };
+        let cells = spray(1);
+        assert_eq!(cells, spray(1));
+        assert_ne!(cells, spray(2));
+
+        let mut count = 0;
+        for cell in &cells {
+            for (block, point) in [
+                (cell.upper_block, cell.upper_point),
+                (cell.lower_block, cell.lower_point),
+            ]
+            .iter()
+            {
+                if block.is_some() {
+                    count += 1;
+                    let (x, y) = (point.x as i32 - 10, point.y as i32 - 10);
+                    assert!(x * x + y * y <= 25);
+                }
+            }
+        }
+        assert!(count > 0);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    canvas::Canvas,
+    terminal::SIZE,
+    util::{Color, Point},
+};
+
+/// The axes that drawing is mirrored across.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Axes {
+    #[default]
+    None,
+    /// Mirrored from left to right across a vertical axis.
+    Horizontal,
+    /// Mirrored from top to bottom across a horizontal axis.
+    Vertical,
+    /// Mirrored across both axes.
+    FourWay,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Symmetry {
+    pub axes: Axes,
+    /// Where the axes cross. The blocks on an axis are their own mirror images.
+    pub center: Point,
+}
+
+impl Symmetry {
+    /// Returns the point and its mirror images, always in the same order for the same axes.
+    /// Mirror images with negative coordinates are `None`.
+    pub fn points(&self, point: Point) -> Vec<Option<Point>> {
+        let count = match self.axes {
+            Axes::None => 1,
+            Axes::Horizontal | Axes::Vertical => 2,
+            Axes::FourWay => 4,
+        };
+        (0..count).map(|index| self.mirror(point, index)).collect()
+    }
+
+    /// Returns the mirror image of the point at the index of [`Symmetry::points`].
+    /// Mirroring a mirror image again returns the point it's the mirror image of.
+    pub fn mirror(&self, point: Point, index: usize) -> Option<Point> {
+        let mirror = |coordinate: SIZE, center: SIZE| {
+            let mirrored = 2 * center as i32 - coordinate as i32;
+            if (0..=SIZE::MAX as i32).contains(&mirrored) {
+                Some(mirrored as SIZE)
+            } else {
+                None
+            }
+        };
+        let mirror_x = |point: Point| {
+            Some(Point {
+                x: mirror(point.x, self.center.x)?,
+                ..point
+            })
+        };
+        let mirror_y = |point: Point| {
+            Some(Point {
+                y: mirror(point.y, self.center.y)?,
+                ..point
+            })
+        };
+        match (self.axes, index) {
+            (_, 0) => Some(point),
+            (Axes::Horizontal | Axes::FourWay, 1) => mirror_x(point),
+            (Axes::Vertical, 1) | (Axes::FourWay, 2) => mirror_y(point),
+            (Axes::FourWay, 3) => mirror_x(point).and_then(mirror_y),
+            _ => None,
+        }
+    }
+
+    /// Returns the pairs of the mirror images of both points where both exist.
+    pub fn point_pairs(&self, first: Point, second: Point) -> Vec<(Point, Point)> {
+        self.points(first)
+            .into_iter()
+            .zip(self.points(second))
+            .filter_map(|pair| match pair {
+                (Some(first), Some(second)) => Some((first, second)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Draws dashed lines where the axes are.
+    pub fn draw_axes(&self, canvas: &mut Canvas) {
+        let size = canvas.size();
+        let dash = |coordinate: SIZE| {
+            if coordinate % 4 < 2 {
+                Color::White
+            } else {
+                Color::Black
+            }
+        };
+        if let Axes::Horizontal | Axes::FourWay = self.axes {
+            for y in 0..size.height * 2 {
+                canvas.block(Point { y, ..self.center }, dash(y));
+            }
+        }
+        if let Axes::Vertical | Axes::FourWay = self.axes {
+            for x in 0..size.width {
+                canvas.block(Point { x, ..self.center }, dash(x));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::tools::{spray::Spray, Tool},
+        util::Size,
+    };
+
+    #[test]
+    fn test_points() {
+        let symmetry = Symmetry {
+            axes: Axes::FourWay,
+            center: Point { x: 3, y: 2 },
+        };
+        assert_eq!(
+            symmetry.points(Point { x: 1, y: 1 }),
+            vec![
+                Some(Point { x: 1, y: 1 }),
+                Some(Point { x: 5, y: 1 }),
+                Some(Point { x: 1, y: 3 }),
+                Some(Point { x: 5, y: 3 }),
+            ]
+        );
+        assert_eq!(
+            symmetry.points(Point { x: 7, y: 5 }),
+            vec![Some(Point { x: 7, y: 5 }), None, None, None]
+        );
+    }
+
+    #[test]
+    fn test_mirrored_tools() {
+        let symmetry = Symmetry {
+            axes: Axes::Horizontal,
+            center: Point { x: 10, y: 10 },
+        };
+        for tool in &[Tool::Spray(Spray::new(30, 1)), Tool::DitheredBrush] {
+            let mut canvas = Canvas::new(Size {
+                width: 21,
+                height: 10,
+            });
+            for (index, point) in symmetry
+                .points(Point { x: 5, y: 9 })
+                .into_iter()
+                .enumerate()
+            {
+                tool.clone().draw(
+                    &mut canvas,
+                    point.unwrap(),
+                    None,
+                    (Color::Red, Color::Blue),
+                    4,
+                    None,
+                    |point| symmetry.mirror(point, index),
+                );
+            }
+            for x in 0..21 {
+                for y in 0..20 {
+                    assert_eq!(
+                        canvas.get_block(Point { x, y }),
+                        canvas.get_block(Point { x: 20 - x, y })
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Flipping, rotating and scaling clips and the whole canvas.
+//!
+//! Everything is transformed block by block so that pixel art stays intact no matter where the half blocks are.
+//! Characters are not blocks: they keep their orientation and are moved to where their cell ends up.
+
+use super::{selection::Clip, Canvas, Cell, MAX_SIZE};
+use crate::{
+    terminal::SIZE,
+    util::{Point, Size},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    FlipHorizontally,
+    FlipVertically,
+    RotateClockwise,
+    RotateCounterclockwise,
+    /// Scales up by an integer factor using nearest-neighbour scaling.
+    Scale(SIZE),
+}
+
+impl Clip {
+    /// Returns the transformed clip. A scaled clip is limited to what fits on the largest possible canvas.
+    pub fn transform(&self, transform: Transform) -> Clip {
+        use Transform::*;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (new_width, new_height) = match transform {
+            RotateClockwise | RotateCounterclockwise => (height, width),
+            Scale(factor) => (
+                (width * factor as usize).min(MAX_SIZE.width as usize),
+                (height * factor as usize).min(MAX_SIZE.height as usize * 2),
+            ),
+            FlipHorizontally | FlipVertically => (width, height),
+        };
+
+        let mut blocks = Vec::with_capacity(new_width * new_height);
+        for y in 0..new_height {
+            for x in 0..new_width {
+                // Where the block comes from
+                let (x, y) = match transform {
+                    FlipHorizontally => (width - 1 - x, y),
+                    FlipVertically => (x, height - 1 - y),
+                    RotateClockwise => (y, height - 1 - x),
+                    RotateCounterclockwise => (width - 1 - y, x),
+                    Scale(factor) => (x / factor as usize, y / factor as usize),
+                };
+                blocks.push(self.blocks[x + y * width]);
+            }
+        }
+
+        let characters = self
+            .characters
+            .iter()
+            .filter_map(|(point, character)| {
+                // A character's cell is one block wide and two blocks high
+                let (x, y) = (point.x as usize, point.y as usize);
+                let (x, y) = match transform {
+                    FlipHorizontally => (width - 1 - x, y),
+                    FlipVertically => (x, height.saturating_sub(y + 2)),
+                    RotateClockwise => (height.saturating_sub(y + 2), x),
+                    RotateCounterclockwise => (y, width - 1 - x),
+                    Scale(factor) => (x * factor as usize, y * factor as usize),
+                };
+                if x < new_width && y < new_height {
+                    Some((
+                        Point {
+                            x: x as SIZE,
+                            y: y as SIZE,
+                        },
+                        *character,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Clip {
+            width: new_width as SIZE,
+            height: new_height as SIZE,
+            blocks,
+            characters,
+        }
+    }
+}
+
+/// Returns the cells of a layer of the given size with the clip in its upper left corner.
+fn layer_cells(clip: &Clip, size: &Size) -> Vec<Cell> {
+    let mut cells = vec![Cell::default(); size.width as usize * size.height as usize];
+    for (index, block) in clip.blocks.iter().enumerate() {
+        let point = Point {
+            x: (index % clip.width as usize) as SIZE,
+            y: (index / clip.width as usize) as SIZE,
+        };
+        if let (Some(color), Some(cell)) = (block, cell(&mut cells, size, point)) {
+            if point.y % 2 == 0 {
+                cell.upper_block = Some(*color);
+            } else {
+                cell.lower_block = Some(*color);
+            }
+        }
+    }
+    for (point, character) in &clip.characters {
+        if let Some(cell) = cell(&mut cells, size, *point) {
+            cell.upper_block = None;
+            cell.lower_block = None;
+            cell.character = Some(*character);
+        }
+    }
+    cells
+}
+
+/// Returns the cell at the given point with its points set, if it's inside of the size.
+fn cell<'a>(cells: &'a mut [Cell], size: &Size, point: Point) -> Option<&'a mut Cell> {
+    if point.x >= size.width || point.y / 2 >= size.height {
+        return None;
+    }
+    let cell = &mut cells[point.x as usize + (point.y / 2) as usize * size.width as usize];
+    let upper_y = point.y - point.y % 2;
+    cell.upper_point = Point {
+        y: upper_y,
+        ..point
+    };
+    cell.lower_point = Point {
+        y: upper_y + 1,
+        ..point
+    };
+    Some(cell)
+}
+
+impl Canvas {
+    /// Transforms all layers. Rotating swaps the width and the height in blocks,
+    /// so the canvas grows by a row of blocks if its new height is not a whole number of cells.
+    pub fn transform(&mut self, transform: Transform) {
+        self.replace(|canvas| {
+            let area = (
+                Point { x: 0, y: 0 },
+                Point {
+                    x: canvas.size.width - 1,
+                    y: canvas.size.height * 2 - 1,
+                },
+            );
+            let current_layer = canvas.layer;
+            let mut clips = Vec::<Clip>::new();
+            for layer in 0..canvas.layers.len() {
+                canvas.layer = layer;
+                clips.push(canvas.copy(area).transform(transform));
+            }
+            canvas.layer = current_layer;
+
+            let size = Canvas::limit_size(Size {
+                width: clips[0].width,
+                height: clips[0].height.div_ceil(2),
+            });
+            for (layer, clip) in canvas.layers.iter_mut().zip(&clips) {
+                layer.cells = layer_cells(clip, &size);
+            }
+            canvas.size = size;
+            canvas.set_viewport(canvas.viewport);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Color;
+
+    #[test]
+    fn test_transform_clip() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 1,
+        });
+        canvas.block(Point { x: 0, y: 0 }, Color::Red);
+        canvas.block(Point { x: 2, y: 1 }, Color::Blue);
+        let clip = canvas.copy((Point { x: 0, y: 0 }, Point { x: 2, y: 1 }));
+
+        let rotated = clip.transform(Transform::RotateClockwise);
+        assert_eq!((rotated.width, rotated.height), (2, 3));
+        assert_eq!(rotated.blocks[1], Some(Color::Red));
+        assert_eq!(rotated.blocks[4], Some(Color::Blue));
+        let rotated = rotated.transform(Transform::RotateCounterclockwise);
+        assert_eq!(rotated.blocks, clip.blocks);
+
+        let flipped = clip.transform(Transform::FlipHorizontally);
+        assert_eq!(flipped.blocks[2], Some(Color::Red));
+        let flipped = clip.transform(Transform::FlipVertically);
+        assert_eq!(flipped.blocks[3], Some(Color::Red));
+
+        let scaled = clip.transform(Transform::Scale(2));
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert_eq!(scaled.blocks[0..2], [Some(Color::Red); 2]);
+        assert_eq!(scaled.blocks[6..8], [Some(Color::Red); 2]);
+        assert_eq!(scaled.blocks[4 + 2 * 6], Some(Color::Blue));
+    }
+
+    #[test]
+    fn test_transform_canvas() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 1,
+        });
+        canvas.block(Point { x: 2, y: 1 }, Color::Blue);
+        canvas.add_layer();
+        canvas.write_character(Point { x: 0, y: 0 }, 'a');
+
+        canvas.transform(Transform::RotateClockwise);
+        assert_eq!(
+            canvas.size(),
+            Size {
+                width: 2,
+                height: 2
+            }
+        );
+        assert_eq!(canvas.current_layer(), 1);
+        assert_eq!(canvas.get_cell(Point { x: 0, y: 0 }).character, Some('a'));
+        canvas.select_layer(0);
+        assert_eq!(canvas.get_block(Point { x: 0, y: 2 }), Some(Color::Blue));
+    }
+}
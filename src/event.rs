@@ -1,14 +1,28 @@
 use crate::{
-    canvas::{tools, Canvas},
+    canvas::{
+        dither::{Dithering, Palette},
+        tools::{
+            self,
+            symmetry::{Axes, Symmetry},
+        },
+        transform::Transform,
+        Canvas,
+    },
+    file,
     palette::{self, colors::ClickableColor},
     terminal::{self, Terminal, SIZE},
-    util::{Color, Point, Size},
+    util::{self, Color, Point},
 };
+use std::path::{Path, PathBuf};
 use terminal::event::{Event, EventKind, KeyEvent, KeyModifier, MouseButton, MouseEvent};
 mod color_picker;
 pub mod input;
 mod key_movement;
+mod preview;
+mod prompt;
+mod selection;
 mod undo_redo;
+mod viewport;
 
 #[derive(Clone, Default)]
 pub struct State {
@@ -16,34 +30,88 @@ pub struct State {
     pub last_point: Option<Point>,
     /// This is set to `Some(...)` and then never set to `None` again.
     pub lasting_last_point: Option<Point>,
+    /// Where the mouse button was pressed when drawing a shape.
+    pub shape_start: Option<Point>,
     pub left_color: Color,
     pub right_color: Color,
-    pub input_field_color: Option<Color>,
     pub tool: tools::Tool,
     pub tool_size: SIZE,
+    /// Whether rectangles and ellipses are filled with the color of the other mouse button.
+    pub fill_shapes: bool,
+    /// The options of the fill bucket. They are kept when changing tools.
+    pub bucket_fill: tools::bucket::Fill,
+    pub gradient: tools::gradient::Gradient,
+    /// How drawing is mirrored.
+    pub symmetry: Symmetry,
+    /// The percentage of the blocks within the tool size that the spray sprays each time.
+    pub spray_density: u8,
+    /// Whether corners are removed from strokes that are one block thin.
+    pub pixel_perfect: bool,
+    /// The strokes being drawn, one for each mirror image.
+    pub strokes: Vec<tools::pixel_perfect::PixelPerfect>,
 }
 
-const HELP: [&str; 8] = [
+const HELP: [&str; 24] = [
     "* Draw pixels using the left and right mouse buttons",
     "* Toggle the palette using Tab and select colors with the left and right mouse buttons",
     "* Use the mouse wheel to adjust brush size",
-    "* Use number keys 1-4 to change tool: 1 = brush, 2 = quill, 3 = rectangle, 4 = fill bucket, 5 = text",
+    "* Use number keys 0-9 to change tool: 1 = brush, 2 = quill, 3 = rectangle, 4 = fill bucket, 5 = text, 6 = line, 7 = ellipse, 8 = select, 9 = eraser, 0 = spray",
+    "* Press 1 again to mix the left and right colors with the brush, X to reduce the colors of the layer with dithering",
+    "* Press P to toggle pixel-perfect strokes that leave out the corners of one block thin lines",
+    "* Drag to draw lines, rectangles and ellipses and press F to toggle filling them with the right color",
+    "* Press N for the polygon tool, click to add corners and double-click or press Enter to close it",
+    "* Press B for the curve tool, drag a line and then its handles, click to add a second handle and press Enter to draw it",
+    "* Press I for the gradient tool and drag to fill the selection or the area the bucket would fill, R to toggle radial and W to toggle filling the whole canvas",
+    "* When using the fill bucket (4), press T to set the tolerance for RGB colors, C to toggle filling diagonally and G to toggle filling all blocks of the color",
+    "* When using the spray (0), press D to set how densely it sprays",
     "* When using the text tool (5), press keys to draw them on the screen for ASCII art",
+    "* Drag the selection (8) to move it or with the right mouse button to copy it, Ctrl+C/Ctrl+X/Ctrl+V to copy/cut/paste, Delete to delete it",
+    "* Press < or > to rotate the selection or the whole canvas, | or - to flip it and * to scale it up",
+    "* Press M to cycle through horizontal, vertical, four-way and no symmetry around the mouse pointer",
     "* Ctrl+Z to undo, Ctrl+Y to redo last action",
-    "* Pick a color from pixels on the canvas using the middle mouse button",
+    "* Pick a color from pixels on the canvas by clicking the middle mouse button",
+    "* Move around the canvas using the arrow keys or by dragging with the middle mouse button",
+    "* Ctrl+O to open a file, PNG image or ANSI art, Ctrl+S to save, Ctrl+A to save as, Ctrl+E to export as PNG or ANSI art (.ans)",
+    "* Ctrl+R to resize the canvas",
+    "* Ctrl+N to add a layer, Ctrl+D to delete it, Page Up/Down to select the layer above/below",
+    "* Ctrl+U/Ctrl+B to move the layer up/down, Ctrl+T to toggle its visibility, Ctrl+G to merge it down",
     "* Press Escape to exit, and H to toggle this help text",
 ];
 
-pub fn main_loop(terminal: &mut Terminal) {
+pub fn main_loop(terminal: &mut Terminal, mut path: Option<PathBuf>) {
     // The main canvas for the image
-    let mut primary_canvas = Canvas::new();
+    let mut primary_canvas = Canvas::new(terminal.size.clone());
 
     // The secondary canvas for things like the palette
-    let mut secondary_canvas = Canvas::new();
+    let mut secondary_canvas = Canvas::new(terminal.size.clone());
+    // The symmetry axes are drawn on the bottom layer so that the previews above them don't remove them
+    secondary_canvas.add_layer();
+
+    // The length of the message in the top left corner that is cleared on the next event
+    let mut message_length = 0;
+
+    set_title(terminal, path.as_deref());
+    if let Some(file_path) = path.take() {
+        // A path to a file that doesn't exist yet is where the new image will be saved to
+        if file_path.exists() {
+            message_length = open(terminal, &mut primary_canvas, &mut path, file_path);
+            secondary_canvas.resize(primary_canvas.size());
+        } else {
+            path = Some(file_path);
+        }
+    }
 
-    let mut save_input_field: Option<crate::input::Field> = None;
     let mut clickable_colors = Vec::<ClickableColor>::new();
-    let mut undo_redo_buffer = undo_redo::UndoRedoBuffer::new();
+    let mut undo_redo_buffer = undo_redo::UndoRedoBuffer::new(&mut primary_canvas);
+    let mut panning = viewport::Panning::default();
+    let mut preview = preview::Preview::default();
+    let mut selection = selection::Selection::default();
+    let mut symmetry_axes = preview::Preview::default();
+    // The symmetry, viewport and size the axes were last drawn for
+    let mut drawn_axes = None;
+    let mut last_event_was_mouse = false;
+    // Where the tool would draw if the mouse button was pressed
+    let mut hover = preview::Preview::default();
     // The `Point` doesn't matter here because it's re-set every time the palette is opened
     let mut palette_input_field = crate::input::Field::new(Point { x: 0, y: 0 }, String::new());
     let mut palette_input_field_clickable_colors = Vec::<ClickableColor>::new();
@@ -51,33 +119,62 @@ pub fn main_loop(terminal: &mut Terminal) {
     let mut state = State {
         left_color: Color::White,
         tool_size: 1,
+        spray_density: 10,
         ..Default::default()
     };
 
     let mut show_help = true;
     let help_current_tool_spaces = "                       ";
 
-    let mut event_happened = false;
-
-    terminal.set_cursor(Point { x: 0, y: 0 });
-    terminal.write("Move your mouse to start!");
-    terminal.flush();
+    if message_length == 0 {
+        message_length = show_message(terminal, "Move your mouse to start!");
+    }
 
     while let Some(event) = terminal.read_event() {
-        if !event_happened {
-            terminal.set_cursor(Point { x: 0, y: 0 });
-            terminal.write("                         ");
+        let changed_points = primary_canvas.changed_points();
+        // Everything the previous event changed is one step
+        undo_redo_buffer.commit(&mut primary_canvas);
+
+        // It's drawn again if the mouse is still only moved
+        hover.clear(&mut primary_canvas, &mut secondary_canvas);
+        let message_cleared = message_length != 0;
+        if message_cleared {
+            clear_message(terminal, &mut primary_canvas, message_length);
+            message_length = 0;
+        }
+        // Anything but the mouse could have redrawn the whole screen.
+        // Otherwise only the blocks drawn over the axes are drawn again.
+        let axes = (
+            state.symmetry,
+            primary_canvas.viewport(),
+            primary_canvas.size(),
+        );
+        if !last_event_was_mouse || message_cleared || drawn_axes.as_ref() != Some(&axes) {
+            draw_symmetry_axes(
+                terminal,
+                &state.symmetry,
+                &mut symmetry_axes,
+                &mut primary_canvas,
+                &mut secondary_canvas,
+            );
+            drawn_axes = Some(axes);
+        } else if state.symmetry.axes != Axes::None && !changed_points.is_empty() {
+            for point in changed_points {
+                secondary_canvas.redraw_cell_over(point, &primary_canvas);
+            }
             terminal.flush();
         }
-        event_happened = true;
+        last_event_was_mouse = matches!(event, Event::Mouse(_));
         if show_help {
             terminal.set_cursor(Point {
-                x: terminal.size.width / 2 - (help_current_tool_spaces.len() as u8) / 2,
+                x: (terminal.size.width / 2)
+                    .saturating_sub(help_current_tool_spaces.len() as SIZE / 2),
                 y: 0,
             });
             terminal.write(help_current_tool_spaces);
             terminal.set_cursor(Point {
-                x: terminal.size.width / 2 - (help_current_tool_spaces.len() as u8) / 2,
+                x: (terminal.size.width / 2)
+                    .saturating_sub(help_current_tool_spaces.len() as SIZE / 2),
                 y: 0,
             });
             use tools::Tool;
@@ -86,18 +183,45 @@ pub fn main_loop(terminal: &mut Terminal) {
                 Tool::Brush => {
                     terminal.write("Current tool: brush");
                 }
+                Tool::DitheredBrush => {
+                    terminal.write("Current tool: dithered brush");
+                }
                 Tool::Quill => {
                     terminal.write("Current tool: quill");
                 }
                 Tool::Rectangle => {
                     terminal.write("Current tool: rectangle");
                 }
-                Tool::Bucket => {
+                Tool::Bucket(_) => {
                     terminal.write("Current tool: bucket");
                 }
                 Tool::Text => {
                     terminal.write("Current tool: text");
                 }
+                Tool::Line => {
+                    terminal.write("Current tool: line");
+                }
+                Tool::Ellipse => {
+                    terminal.write("Current tool: ellipse");
+                }
+                Tool::Select => {
+                    terminal.write("Current tool: select");
+                }
+                Tool::Eraser => {
+                    terminal.write("Current tool: eraser");
+                }
+                Tool::Spray(_) => {
+                    terminal.write("Current tool: spray");
+                }
+                Tool::Gradient(_) => {
+                    terminal.write("Current tool: gradient");
+                }
+                Tool::Polygon(_) => {
+                    terminal.write("Current tool: polygon");
+                }
+                Tool::Curve(_) => {
+                    terminal.write("Current tool: curve");
+                }
             }
             terminal.write("\x1b[0m");
 
@@ -112,7 +236,20 @@ pub fn main_loop(terminal: &mut Terminal) {
             terminal.flush();
         }
 
-        if undo_redo::handle(&event, terminal, &mut primary_canvas, &mut undo_redo_buffer) {
+        // Undoing while moving would lose or duplicate what is being moved
+        if !selection.is_dragging()
+            && undo_redo::handle(&event, terminal, &mut primary_canvas, &mut undo_redo_buffer)
+        {
+            // Undoing or redoing a resize changes the size
+            if secondary_canvas.size() != primary_canvas.size() {
+                secondary_canvas.resize(primary_canvas.size());
+            }
+            secondary_canvas.set_viewport(primary_canvas.viewport());
+            continue;
+        }
+
+        if viewport::handle(&event, terminal, &mut primary_canvas, &mut panning) {
+            secondary_canvas.set_viewport(primary_canvas.viewport());
             continue;
         }
 
@@ -120,61 +257,216 @@ pub fn main_loop(terminal: &mut Terminal) {
             continue;
         }
 
+        if selection::handle(
+            &event,
+            terminal,
+            &mut state,
+            &mut primary_canvas,
+            &mut secondary_canvas,
+            &mut selection,
+            &mut preview,
+            &mut undo_redo_buffer,
+        ) {
+            continue;
+        }
+
         // This is for the text tool.
-        match event {
-            Event::Key(ref key) => match key {
-                KeyEvent::Char(c, _) => {
-                    if let Some(point) = state.lasting_last_point {
-                        use tools::Tool::*;
-                        if state.tool == Text {
-                            primary_canvas.write_character(point, *c);
-                            terminal.set_cursor(point);
-                            terminal.write(&c.to_string());
-                            terminal.flush();
-                        }
-                    }
+        if let Event::Key(KeyEvent::Char(c, None)) = event {
+            if let Some(point) = state.lasting_last_point {
+                use tools::Tool::*;
+                if state.tool == Text && primary_canvas.contains(point) {
+                    primary_canvas.write_character(point, c);
+                    primary_canvas.redraw_cell(point);
+                    terminal.flush();
                 }
-                _ => {}
-            },
-            _ => {}
+            }
         }
 
         match event {
-            Event::Mouse(MouseEvent { kind, point }) => match kind {
+            Event::Mouse(MouseEvent { kind, point }) => match &kind {
                 EventKind::Drag(button) | EventKind::Press(button) => {
-                    let color = match button {
-                        MouseButton::Left => state.left_color,
-                        MouseButton::Right => state.right_color,
+                    let (color, other_color) = match button {
+                        MouseButton::Left => (state.left_color, state.right_color),
+                        MouseButton::Right => (state.right_color, state.left_color),
                         _ => continue,
                     };
-                    let point = Point {
-                        y: point.y * 2,
-                        ..point
-                    };
-                    state.tool.draw(
-                        &mut primary_canvas,
-                        point,
-                        state.last_point,
-                        color,
-                        state.tool_size,
-                    );
-                    undo_redo_buffer.push(undo_redo::Operation {
-                        tool: state.tool.clone(),
-                        start: point,
-                        end: state.last_point,
-                        color,
-                        size: state.tool_size,
-                    });
+                    if let tools::Tool::Polygon(polygon) = &mut state.tool {
+                        if let EventKind::Press(_) = kind {
+                            let point = primary_canvas.canvas_point(point);
+                            let size = state.tool_size;
+                            if polygon.add_vertex(point, (color, other_color)) {
+                                preview.clear(&mut primary_canvas, &mut secondary_canvas);
+                                polygon.close(&mut primary_canvas, state.fill_shapes, size);
+                            } else {
+                                preview.draw(
+                                    &mut primary_canvas,
+                                    &mut secondary_canvas,
+                                    |canvas| polygon.draw_sides(canvas, None, size),
+                                );
+                            }
+                            state.lasting_last_point = Some(point);
+                            terminal.flush();
+                        }
+                        continue;
+                    }
+                    if let tools::Tool::Curve(curve) = &mut state.tool {
+                        let point = primary_canvas.canvas_point(point);
+                        let size = state.tool_size;
+                        match kind {
+                            EventKind::Press(_) => {
+                                // Clicking away from a curve that can't get more control points draws it
+                                if !curve.grab(point) {
+                                    curve.commit(&mut primary_canvas, size);
+                                    curve.start(point, color);
+                                }
+                            }
+                            _ => curve.drag(point),
+                        }
+                        draw_curve(
+                            curve,
+                            size,
+                            &mut preview,
+                            &mut primary_canvas,
+                            &mut secondary_canvas,
+                        );
+                        state.lasting_last_point = Some(point);
+                        terminal.flush();
+                        continue;
+                    }
+                    let fill_color = Some(other_color).filter(|_| state.fill_shapes);
+                    // Everything drawn until the button is released can be undone at once
+                    undo_redo_buffer.start_stroke();
+                    let point = primary_canvas.canvas_point(point);
+                    let symmetry = state.symmetry;
+                    let pixel_perfect = state.pixel_perfect;
+                    if state.tool.draws_shape() {
+                        let start = *state.shape_start.get_or_insert(point);
+                        let tool = &state.tool;
+                        let size = state.tool_size;
+                        // Gradients are not mirrored
+                        let symmetry = match tool {
+                            tools::Tool::Gradient(_) => Symmetry::default(),
+                            _ => symmetry,
+                        };
+                        preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+                            for (start, end) in symmetry.point_pairs(start, point) {
+                                tool.draw_shape(canvas, start, end, color, fill_color, size);
+                            }
+                        });
+                    } else if let Some(last_point) = state.last_point {
+                        // Each mirror image is drawn with the tool as it was before so that the spray sprays the same
+                        let tool = state.tool.clone();
+                        // The strokes are kept per mirror image, including the ones that don't exist
+                        let pairs = symmetry
+                            .points(point)
+                            .into_iter()
+                            .zip(symmetry.points(last_point));
+                        for (index, pair) in pairs.enumerate() {
+                            let (point, last_point) = match pair {
+                                (Some(point), Some(last_point)) => (point, last_point),
+                                _ => continue,
+                            };
+                            state.tool = tool.clone();
+                            state.tool.draw(
+                                &mut primary_canvas,
+                                point,
+                                Some(last_point),
+                                (color, other_color),
+                                state.tool_size,
+                                state.strokes.get_mut(index).filter(|_| pixel_perfect),
+                                |point| symmetry.mirror(point, index),
+                            );
+                        }
+                    } else {
+                        let tool = state.tool.clone();
+                        let points = symmetry.points(point);
+                        state.strokes = vec![Default::default(); points.len()];
+                        for (index, point) in points.into_iter().enumerate() {
+                            let point = match point {
+                                Some(point) => point,
+                                None => continue,
+                            };
+                            state.tool = tool.clone();
+                            state.tool.draw(
+                                &mut primary_canvas,
+                                point,
+                                None,
+                                (color, other_color),
+                                state.tool_size,
+                                state.strokes.get_mut(index).filter(|_| pixel_perfect),
+                                |point| symmetry.mirror(point, index),
+                            );
+                        }
+                    }
                     state.last_point = Some(point);
                     state.lasting_last_point = Some(point);
                     terminal.flush();
                 }
+                EventKind::Release(button @ (MouseButton::Left | MouseButton::Right)) => {
+                    if let tools::Tool::Curve(curve) = &mut state.tool {
+                        curve.release();
+                        draw_curve(
+                            curve,
+                            state.tool_size,
+                            &mut preview,
+                            &mut primary_canvas,
+                            &mut secondary_canvas,
+                        );
+                        terminal.flush();
+                    }
+                    if let Some(start) = state.shape_start.take() {
+                        let (color, other_color) = match button {
+                            MouseButton::Left => (state.left_color, state.right_color),
+                            _ => (state.right_color, state.left_color),
+                        };
+                        let fill_color = Some(other_color).filter(|_| state.fill_shapes);
+                        preview.clear(&mut primary_canvas, &mut secondary_canvas);
+                        let end = primary_canvas.canvas_point(point);
+                        if let tools::Tool::Gradient(gradient) = state.tool {
+                            let points = match selection.area() {
+                                Some(area) => primary_canvas.area_points(area),
+                                None if gradient.whole_canvas => primary_canvas.area_points((
+                                    Point { x: 0, y: 0 },
+                                    Point {
+                                        x: SIZE::MAX,
+                                        y: SIZE::MAX,
+                                    },
+                                )),
+                                None => primary_canvas.bucket_area(start, state.bucket_fill),
+                            };
+                            primary_canvas.gradient(
+                                &points,
+                                start,
+                                end,
+                                (color, other_color),
+                                gradient,
+                                Terminal::supports_truecolor(),
+                            );
+                            selection.redraw(&mut primary_canvas, &mut secondary_canvas);
+                        } else {
+                            for (start, end) in state.symmetry.point_pairs(start, end) {
+                                state.tool.draw_shape(
+                                    &mut primary_canvas,
+                                    start,
+                                    end,
+                                    color,
+                                    fill_color,
+                                    state.tool_size,
+                                );
+                            }
+                        }
+                        terminal.flush();
+                    }
+                    undo_redo_buffer.end_stroke(&mut primary_canvas);
+                    state.last_point = None;
+                    state.strokes.clear();
+                }
                 EventKind::Release(MouseButton::Middle) => {
                     // TODO: this should perhaps not mutate the primary canvas
                     color_picker::handle_events(terminal, &mut primary_canvas, &mut state, point);
                 }
                 EventKind::ScrollUp => {
-                    state.tool_size += 1;
+                    state.tool_size = state.tool_size.saturating_add(1);
                 }
                 EventKind::ScrollDown => {
                     if state.tool_size != 1 {
@@ -182,49 +474,36 @@ pub fn main_loop(terminal: &mut Terminal) {
                     }
                 }
                 EventKind::Move => {
+                    let point = primary_canvas.canvas_point(point);
                     state.lasting_last_point = Some(point);
-
-                    continue;
-
-                    for (index, secondary_cell) in secondary_canvas.cells.clone().iter().enumerate()
-                    {
-                        if secondary_cell.upper_block.is_some() {
-                            let primary_cell = &primary_canvas.cells[index];
-                            primary_canvas.block(
-                                secondary_cell.upper_point,
-                                primary_cell.upper_block.unwrap_or_default(),
-                            );
+                    let tool = &state.tool;
+                    let size = state.tool_size;
+                    // The preview could be covered by where the tool was before
+                    match tool {
+                        tools::Tool::Polygon(polygon) => {
+                            preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+                                polygon.draw_sides(canvas, Some(point), size)
+                            });
                         }
-                        if secondary_cell.lower_block.is_some() {
-                            let primary_cell = &primary_canvas.cells[index];
-                            primary_canvas.block(
-                                secondary_cell.lower_point,
-                                primary_cell.lower_block.unwrap_or_default(),
+                        tools::Tool::Curve(curve) => {
+                            draw_curve(
+                                curve,
+                                size,
+                                &mut preview,
+                                &mut primary_canvas,
+                                &mut secondary_canvas,
                             );
                         }
+                        _ => {}
                     }
-
-                    secondary_canvas.clear();
-
-                    let point = Point {
-                        y: point.y * 2,
-                        ..point
-                    };
-                    state.tool.draw(
-                        &mut secondary_canvas,
-                        point,
-                        state.last_point,
-                        state.left_color, // TODO: this should eventually be the color below the block being hovered but inverted. maybe with closures
-                        state.tool_size,
-                    );
-                    state.last_point = Some(point);
-                    state.lasting_last_point = Some(point);
-
+                    let points = state.symmetry.points(point);
+                    hover.draw_inverted(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+                        for point in points.into_iter().flatten() {
+                            tool.draw_footprint(canvas, point, size);
+                        }
+                    });
                     terminal.flush();
                 }
-                _ => {
-                    state.last_point = None;
-                }
             },
             Event::Key(key) => match key {
                 KeyEvent::Tab => {
@@ -232,7 +511,7 @@ pub fn main_loop(terminal: &mut Terminal) {
                         palette::colors::draw(terminal, &mut clickable_colors, &state);
                     palette_input_field_point.x += 1;
                     palette_input_field.set_point(palette_input_field_point);
-                    if let Some(last_clickable_color) = palette_input_field_clickable_colors.get(0)
+                    if let Some(last_clickable_color) = palette_input_field_clickable_colors.first()
                     {
                         terminal.set_background_color(last_clickable_color.color);
                         palette_input_field.write(' ');
@@ -243,7 +522,7 @@ pub fn main_loop(terminal: &mut Terminal) {
                     terminal.flush();
                     palette::events::handle(
                         terminal,
-                        &mut clickable_colors,
+                        &clickable_colors,
                         &mut state,
                         &mut palette_input_field,
                         &mut palette_input_field_clickable_colors,
@@ -256,46 +535,324 @@ pub fn main_loop(terminal: &mut Terminal) {
                     terminal.hide_cursor();
                     terminal.flush();
                 }
-                KeyEvent::Char(tool @ '1'..='9', _) => {
+                KeyEvent::Char(tool @ '0'..='9', _) => {
+                    finish_shape(
+                        &mut state,
+                        &mut preview,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
                     use tools::Tool::*;
                     state.tool = match tool {
+                        // Pressing it again switches between mixing the colors or not
+                        '1' if state.tool == Brush => DitheredBrush,
                         '1' => Brush,
                         '2' => Quill,
                         '3' => Rectangle,
-                        '4' => Bucket,
+                        '4' => Bucket(state.bucket_fill),
                         '5' => Text,
+                        '6' => Line,
+                        '7' => Ellipse,
+                        '8' => Select,
+                        '9' => Eraser,
+                        '0' => Spray(tools::spray::Spray::new(
+                            state.spray_density,
+                            util::Random::from_time().next(),
+                        )),
                         _ => continue, //todo!(),
                     };
                 }
-                //KeyEvent::Char('s', modifier) => {
-                //    if let Some(KeyModifier::Control) = modifier {
-                //        if save_input_field.is_none() {
-                //            let rows = 3;
-                //            let size = Size {
-                //                width: 20,
-                //                height: rows * 2,
-                //            };
-                //            let border_point = terminal.get_centered_border_point(&size);
-                //            secondary_canvas.hollow_rectangle(border_point, size, Color::White);
-                //            terminal.flush();
-                //            let input_field = crate::input::Field::new(
-                //                Point {
-                //                    x: border_point.x + 1,
-                //                    y: border_point.y + 1,
-                //                },
-                //                String::new(),
-                //            );
-                //            save_input_field = Some(input_field);
-                //            if let Some(mut input_field) = save_input_field {
-                //                loop {
-                //                    if let Some(event) = terminal.read_event() {
-                //                        input::handle(&event, terminal, &mut input_field);
-                //                    }
-                //                }
-                //            }
-                //        }
-                //    }
-                //}
+                KeyEvent::Char('x' | 'X', None) if state.tool != tools::Tool::Text => {
+                    let dithering = prompt::handle(
+                        terminal,
+                        "Bayer or Floyd-Steinberg",
+                        String::from("Floyd-Steinberg"),
+                    );
+                    let palette = dithering.as_ref().and_then(|_| {
+                        prompt::handle(terminal, "Colors (16 or 256)", String::from("256"))
+                    });
+                    terminal.clear();
+                    primary_canvas.redraw();
+                    if let (Some(dithering), Some(palette)) = (dithering, palette) {
+                        let dithering = if dithering.to_lowercase().contains("bayer") {
+                            Dithering::Bayer
+                        } else {
+                            Dithering::FloydSteinberg
+                        };
+                        let palette = if palette.trim() == "16" {
+                            Palette::FourBit
+                        } else {
+                            Palette::EightBit
+                        };
+                        primary_canvas.dither(dithering, palette);
+                    }
+                    terminal.flush();
+                }
+                KeyEvent::Char('m' | 'M', None) if state.tool != tools::Tool::Text => {
+                    let (axes, message) = match state.symmetry.axes {
+                        Axes::None => (Axes::Horizontal, "Horizontal symmetry"),
+                        Axes::Horizontal => (Axes::Vertical, "Vertical symmetry"),
+                        Axes::Vertical => (Axes::FourWay, "Four-way symmetry"),
+                        Axes::FourWay => (Axes::None, "No symmetry"),
+                    };
+                    let size = primary_canvas.size();
+                    state.symmetry = Symmetry {
+                        axes,
+                        center: state.lasting_last_point.unwrap_or(Point {
+                            x: size.width / 2,
+                            y: size.height,
+                        }),
+                    };
+                    draw_symmetry_axes(
+                        terminal,
+                        &state.symmetry,
+                        &mut symmetry_axes,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
+                    message_length = show_message(terminal, message);
+                }
+                KeyEvent::Char('p' | 'P', None) if state.tool != tools::Tool::Text => {
+                    state.pixel_perfect = !state.pixel_perfect;
+                    message_length = show_message(
+                        terminal,
+                        if state.pixel_perfect {
+                            "Pixel-perfect strokes"
+                        } else {
+                            "Strokes with corners"
+                        },
+                    );
+                }
+                KeyEvent::Char('n' | 'N', None) if state.tool != tools::Tool::Text => {
+                    finish_shape(
+                        &mut state,
+                        &mut preview,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
+                    state.tool = tools::Tool::Polygon(Default::default());
+                    terminal.flush();
+                }
+                KeyEvent::Char('b' | 'B', None) if state.tool != tools::Tool::Text => {
+                    finish_shape(
+                        &mut state,
+                        &mut preview,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
+                    state.tool = tools::Tool::Curve(Default::default());
+                    terminal.flush();
+                }
+                KeyEvent::Enter => {
+                    finish_shape(
+                        &mut state,
+                        &mut preview,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
+                    terminal.flush();
+                }
+                KeyEvent::Char('i' | 'I', None) if state.tool != tools::Tool::Text => {
+                    finish_shape(
+                        &mut state,
+                        &mut preview,
+                        &mut primary_canvas,
+                        &mut secondary_canvas,
+                    );
+                    state.tool = tools::Tool::Gradient(state.gradient);
+                    terminal.flush();
+                }
+                KeyEvent::Char(key @ ('r' | 'R' | 'w' | 'W'), None)
+                    if matches!(state.tool, tools::Tool::Gradient(_)) =>
+                {
+                    let gradient = &mut state.gradient;
+                    let message = if matches!(key, 'r' | 'R') {
+                        gradient.radial = !gradient.radial;
+                        if gradient.radial {
+                            "Radial gradient"
+                        } else {
+                            "Linear gradient"
+                        }
+                    } else {
+                        gradient.whole_canvas = !gradient.whole_canvas;
+                        if gradient.whole_canvas {
+                            "Filling the whole canvas"
+                        } else {
+                            "Filling the area the bucket would fill"
+                        }
+                    };
+                    state.tool = tools::Tool::Gradient(state.gradient);
+                    message_length = show_message(terminal, message);
+                }
+                KeyEvent::Char('s', Some(KeyModifier::Control)) => {
+                    message_length = save(terminal, &mut primary_canvas, &mut path, false);
+                }
+                KeyEvent::Char('a', Some(KeyModifier::Control)) => {
+                    message_length = save(terminal, &mut primary_canvas, &mut path, true);
+                }
+                KeyEvent::Char('o', Some(KeyModifier::Control)) => {
+                    let input = prompt::handle(terminal, "Open", String::new());
+                    terminal.clear();
+                    primary_canvas.redraw();
+                    terminal.flush();
+                    if let Some(input) = input {
+                        let file_path = PathBuf::from(input);
+                        message_length =
+                            open(terminal, &mut primary_canvas, &mut path, file_path.clone());
+                        secondary_canvas.resize(primary_canvas.size());
+                        // Opening a file in the native format starts over so that undoing can't bring back
+                        // the previous image and save it to that file. Imports can be undone.
+                        if path.as_ref() == Some(&file_path) {
+                            undo_redo_buffer = undo_redo::UndoRedoBuffer::new(&mut primary_canvas);
+                        }
+                    }
+                }
+                KeyEvent::Char('r', Some(KeyModifier::Control)) => {
+                    let size = primary_canvas.size();
+                    let input = prompt::handle(
+                        terminal,
+                        "Canvas size",
+                        format!("{}x{}", size.width, size.height),
+                    );
+                    if let Some(size) = input.as_deref().and_then(util::parse_size) {
+                        primary_canvas.resize(size.clone());
+                        secondary_canvas.resize(size);
+                    }
+                    terminal.clear();
+                    primary_canvas.redraw();
+                    terminal.flush();
+                }
+                KeyEvent::Char('e', Some(KeyModifier::Control)) => {
+                    message_length = export(terminal, &mut primary_canvas, path.as_deref());
+                }
+                KeyEvent::Char(
+                    key @ ('n' | 'd' | 'u' | 'b' | 't' | 'g'),
+                    Some(KeyModifier::Control),
+                ) => {
+                    match key {
+                        'n' => primary_canvas.add_layer(),
+                        'd' => primary_canvas.remove_layer(),
+                        'u' => primary_canvas.move_layer_up(),
+                        'b' => primary_canvas.move_layer_down(),
+                        't' => primary_canvas.toggle_layer_visibility(),
+                        'g' => primary_canvas.merge_down(),
+                        _ => unreachable!(),
+                    }
+                    terminal.clear();
+                    primary_canvas.redraw();
+                    message_length = show_layer(terminal, &primary_canvas);
+                }
+                KeyEvent::PageUp => {
+                    primary_canvas.select_layer(primary_canvas.current_layer() + 1);
+                    message_length = show_layer(terminal, &primary_canvas);
+                }
+                KeyEvent::PageDown => {
+                    primary_canvas.select_layer(primary_canvas.current_layer().saturating_sub(1));
+                    message_length = show_layer(terminal, &primary_canvas);
+                }
+                KeyEvent::Char('f' | 'F', None) if state.tool != tools::Tool::Text => {
+                    state.fill_shapes = !state.fill_shapes;
+                    message_length = show_message(
+                        terminal,
+                        if state.fill_shapes {
+                            "Filling shapes"
+                        } else {
+                            "Not filling shapes"
+                        },
+                    );
+                }
+                KeyEvent::Char(key @ ('<' | '>' | '|' | '-' | '*'), None)
+                    if state.tool != tools::Tool::Text =>
+                {
+                    let transform = match key {
+                        '<' => Transform::RotateCounterclockwise,
+                        '>' => Transform::RotateClockwise,
+                        '|' => Transform::FlipHorizontally,
+                        '-' => Transform::FlipVertically,
+                        _ => {
+                            let input = prompt::handle(terminal, "Scale by", String::from("2"));
+                            terminal.clear();
+                            primary_canvas.redraw();
+                            match input.and_then(|input| input.trim().parse::<SIZE>().ok()) {
+                                Some(factor) if factor > 1 => Transform::Scale(factor),
+                                _ => {
+                                    terminal.flush();
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    if !selection.transform(&mut primary_canvas, &mut secondary_canvas, transform) {
+                        primary_canvas.transform(transform);
+                        secondary_canvas.resize(primary_canvas.size());
+                        secondary_canvas.set_viewport(primary_canvas.viewport());
+                        terminal.clear();
+                        primary_canvas.redraw();
+                    }
+                    terminal.flush();
+                }
+                KeyEvent::Char(key @ ('t' | 'T' | 'c' | 'C' | 'g' | 'G'), None)
+                    if matches!(state.tool, tools::Tool::Bucket(_)) =>
+                {
+                    let fill = &mut state.bucket_fill;
+                    let message = match key.to_ascii_lowercase() {
+                        't' => {
+                            let input = prompt::handle(
+                                terminal,
+                                "Tolerance (0-255)",
+                                fill.tolerance.to_string(),
+                            );
+                            terminal.clear();
+                            primary_canvas.redraw();
+                            if let Some(tolerance) =
+                                input.and_then(|input| input.trim().parse::<u8>().ok())
+                            {
+                                fill.tolerance = tolerance;
+                            }
+                            format!("Tolerance: {}", fill.tolerance)
+                        }
+                        'c' => {
+                            fill.diagonal = !fill.diagonal;
+                            if fill.diagonal {
+                                "Filling diagonally connected blocks"
+                            } else {
+                                "Filling only directly connected blocks"
+                            }
+                            .to_string()
+                        }
+                        _ => {
+                            fill.global = !fill.global;
+                            if fill.global {
+                                "Filling all blocks of the color"
+                            } else {
+                                "Filling connected blocks"
+                            }
+                            .to_string()
+                        }
+                    };
+                    state.tool = tools::Tool::Bucket(state.bucket_fill);
+                    message_length = show_message(terminal, &message);
+                }
+                KeyEvent::Char('d' | 'D', None) if matches!(state.tool, tools::Tool::Spray(_)) => {
+                    let input = prompt::handle(
+                        terminal,
+                        "Density (1-100%)",
+                        state.spray_density.to_string(),
+                    );
+                    terminal.clear();
+                    primary_canvas.redraw();
+                    if let Some(density) = input
+                        .and_then(|input| input.trim().trim_end_matches('%').parse::<u8>().ok())
+                        .filter(|density| (1..=100).contains(density))
+                    {
+                        state.spray_density = density;
+                    }
+                    if let tools::Tool::Spray(spray) = &mut state.tool {
+                        spray.density = state.spray_density;
+                    }
+                    message_length =
+                        show_message(terminal, &format!("Density: {}%", state.spray_density));
+                }
                 KeyEvent::Char('c', Some(KeyModifier::Control)) => break,
                 KeyEvent::Char('h', _) | KeyEvent::Char('H', _) => {
                     show_help = !show_help;
@@ -310,7 +867,8 @@ pub fn main_loop(terminal: &mut Terminal) {
                             }
                         }
                         terminal.set_cursor(Point {
-                            x: terminal.size.width / 2 - (help_current_tool_spaces.len() as u8) / 2,
+                            x: (terminal.size.width / 2)
+                                .saturating_sub(help_current_tool_spaces.len() as SIZE / 2),
                             y: 0,
                         });
                         terminal.write(help_current_tool_spaces);
@@ -331,3 +889,239 @@ pub fn main_loop(terminal: &mut Terminal) {
         }
     }
 }
+
+/// Opens the file at `file_path` in the format of its extension
+/// and sets `path` to where the canvas is saved to from now on.
+///
+/// Returns the length of the message shown.
+fn open(
+    terminal: &mut Terminal,
+    canvas: &mut Canvas,
+    path: &mut Option<PathBuf>,
+    file_path: PathBuf,
+) -> usize {
+    let result = match extension(&file_path).as_deref() {
+        // Images and ANSI art are not saved in the native format so they have to be saved somewhere else
+        Some("png") => file::image::import_png(
+            canvas,
+            &file_path,
+            &terminal.size,
+            Terminal::supports_truecolor(),
+        )
+        .map(|()| None),
+        Some("ans") | Some("txt") => file::ansi::import_ansi(canvas, &file_path).map(|()| None),
+        _ => file::native::load(canvas, &file_path).map(|()| Some(file_path.clone())),
+    };
+
+    match result {
+        Ok(new_path) => {
+            *path = new_path;
+            set_title(terminal, path.as_deref());
+            terminal.clear();
+            canvas.redraw();
+            terminal.flush();
+            0
+        }
+        Err(error) => show_message(
+            terminal,
+            &format!("Opening {} failed: {}", file_path.display(), error),
+        ),
+    }
+}
+
+/// Saves the canvas to the path, asking for a path first if there is none yet or `save_as` is set.
+///
+/// Returns the length of the message shown.
+fn save(
+    terminal: &mut Terminal,
+    canvas: &mut Canvas,
+    path: &mut Option<PathBuf>,
+    save_as: bool,
+) -> usize {
+    let file_path = match path {
+        Some(path) if !save_as => path.clone(),
+        _ => {
+            let input = path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            let input = prompt::handle(terminal, "Save as", input);
+            terminal.clear();
+            canvas.redraw();
+            match input {
+                Some(input) => PathBuf::from(input),
+                None => {
+                    terminal.flush();
+                    return 0;
+                }
+            }
+        }
+    };
+
+    // The file would be opened as an image or ANSI art
+    if let Some("png" | "ans" | "txt") = extension(&file_path).as_deref() {
+        return show_message(
+            terminal,
+            "Saving failed: use Ctrl+E to export as PNG or ANSI art",
+        );
+    }
+    let message = match file::native::save(canvas, &file_path) {
+        Ok(()) => {
+            set_title(terminal, Some(&file_path));
+            format!("Saved to {}", file_path.display())
+        }
+        Err(error) => format!("Saving to {} failed: {}", file_path.display(), error),
+    };
+    *path = Some(file_path);
+    show_message(terminal, &message)
+}
+
+/// Asks for a path and exports the canvas to it in the format of the path's extension.
+///
+/// Returns the length of the message shown.
+fn export(terminal: &mut Terminal, canvas: &mut Canvas, path: Option<&Path>) -> usize {
+    let input = path
+        .unwrap_or_else(|| Path::new("untitled"))
+        .with_extension("png")
+        .display()
+        .to_string();
+    let input = prompt::handle(terminal, "Export as", input);
+    terminal.clear();
+    canvas.redraw();
+    let export_path = match input {
+        Some(input) => PathBuf::from(input),
+        None => {
+            terminal.flush();
+            return 0;
+        }
+    };
+
+    let result = match extension(&export_path).as_deref() {
+        Some("png") => file::image::export_png(canvas, &export_path),
+        Some("ans") | Some("txt") => file::ansi::export_ansi(canvas, &export_path),
+        _ => {
+            return show_message(terminal, "Exporting failed: unsupported file extension");
+        }
+    };
+    let message = match result {
+        Ok(()) => format!("Exported to {}", export_path.display()),
+        Err(error) => format!("Exporting to {} failed: {}", export_path.display(), error),
+    };
+    show_message(terminal, &message)
+}
+
+fn set_title(terminal: &mut Terminal, path: Option<&Path>) {
+    let name = path
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_else(|| "Untitled".into());
+    terminal.set_title(&format!("BlockPaint ({})", name));
+}
+
+/// Draws the axes of the symmetry over the canvas or removes them if there is no symmetry.
+fn draw_symmetry_axes(
+    terminal: &mut Terminal,
+    symmetry: &Symmetry,
+    preview: &mut preview::Preview,
+    primary_canvas: &mut Canvas,
+    secondary_canvas: &mut Canvas,
+) {
+    let layer = secondary_canvas.current_layer();
+    secondary_canvas.select_layer(0);
+    if symmetry.axes == Axes::None {
+        preview.clear(primary_canvas, secondary_canvas);
+    } else {
+        preview.draw(primary_canvas, secondary_canvas, |canvas| {
+            symmetry.draw_axes(canvas)
+        });
+    }
+    secondary_canvas.select_layer(layer);
+    terminal.flush();
+}
+
+/// Draws the polygon or curve being drawn so that it isn't lost when switching tools.
+fn finish_shape(
+    state: &mut State,
+    preview: &mut preview::Preview,
+    primary_canvas: &mut Canvas,
+    secondary_canvas: &mut Canvas,
+) {
+    match &mut state.tool {
+        tools::Tool::Polygon(polygon) => {
+            preview.clear(primary_canvas, secondary_canvas);
+            polygon.close(primary_canvas, state.fill_shapes, state.tool_size);
+        }
+        tools::Tool::Curve(curve) => {
+            preview.clear(primary_canvas, secondary_canvas);
+            curve.commit(primary_canvas, state.tool_size);
+        }
+        _ => {}
+    }
+}
+
+/// Draws the curve with its handles in the inverted colors of the blocks below them.
+fn draw_curve(
+    curve: &tools::curve::Curve,
+    thickness: SIZE,
+    preview: &mut preview::Preview,
+    primary_canvas: &mut Canvas,
+    secondary_canvas: &mut Canvas,
+) {
+    let handles = curve
+        .handles()
+        .iter()
+        .filter(|handle| primary_canvas.contains(**handle))
+        .map(|handle| {
+            let color = primary_canvas.composite_block(*handle).unwrap_or_default();
+            (*handle, color.invert())
+        })
+        .collect::<Vec<(Point, Color)>>();
+    preview.draw(primary_canvas, secondary_canvas, |canvas| {
+        curve.draw(canvas, thickness);
+        for (handle, color) in handles {
+            canvas.block(handle, color);
+        }
+    });
+}
+
+/// Shows which layer is selected and returns the length of the message.
+fn show_layer(terminal: &mut Terminal, canvas: &Canvas) -> usize {
+    let layer = canvas.current_layer();
+    let visibility = if canvas.layers()[layer].visible {
+        ""
+    } else {
+        " (hidden)"
+    };
+    show_message(
+        terminal,
+        &format!(
+            "Layer {} of {}{}",
+            layer + 1,
+            canvas.layers().len(),
+            visibility
+        ),
+    )
+}
+
+/// Shows a message in the top left corner and returns its length.
+fn show_message(terminal: &mut Terminal, message: &str) -> usize {
+    terminal.set_cursor(Point { x: 0, y: 0 });
+    terminal.write(message);
+    terminal.flush();
+    message.chars().count()
+}
+
+/// Clears the message in the top left corner and restores the cells below it.
+fn clear_message(terminal: &mut Terminal, canvas: &mut Canvas, length: usize) {
+    terminal.set_cursor(Point { x: 0, y: 0 });
+    terminal.write(&" ".repeat(length));
+    for x in 0..length.min(SIZE::MAX as usize) {
+        canvas.redraw_cell(canvas.canvas_point(Point { x: x as SIZE, y: 0 }));
+    }
+    terminal.flush();
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
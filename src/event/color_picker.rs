@@ -1,6 +1,5 @@
 use crate::{
     canvas::Canvas,
-    canvas::Cell,
     terminal::{
         event::{Event, EventKind, MouseButton, MouseEvent},
         Terminal,
@@ -14,46 +13,37 @@ pub fn handle_events(
     state: &mut crate::event::State,
     initial_point: Point,
 ) {
-    let (cell, color) = get_cell_and_color(canvas, initial_point);
-    draw_indicator(terminal, canvas, initial_point, &cell.clone(), color);
+    let color = get_color(canvas, initial_point);
+    draw_indicator(terminal, canvas, initial_point, color);
 
     while let Some(event) = terminal.read_event() {
-        match event {
-            Event::Mouse(MouseEvent { kind, point }) => {
-                let (cell, color) = get_cell_and_color(canvas, point);
+        if let Event::Mouse(MouseEvent { kind, point }) = event {
+            let color = get_color(canvas, point);
 
-                match kind {
-                    EventKind::Release(mouse_button) => {
-                        match mouse_button {
-                            MouseButton::Left => {
-                                state.left_color = color;
-                            }
-                            MouseButton::Right => {
-                                state.right_color = color;
-                            }
-                            _ => {}
+            match kind {
+                EventKind::Release(mouse_button) => {
+                    match mouse_button {
+                        MouseButton::Left => {
+                            state.left_color = color;
                         }
-                        terminal.flush();
-                        break;
-                    }
-                    _ => {
-                        draw_indicator(terminal, canvas, point, &cell.clone(), color);
+                        MouseButton::Right => {
+                            state.right_color = color;
+                        }
+                        _ => {}
                     }
+                    terminal.flush();
+                    break;
+                }
+                _ => {
+                    draw_indicator(terminal, canvas, point, color);
                 }
             }
-            _ => {}
         }
     }
 }
 
 /// Draws the indicator that indicates where the pointer is at.
-fn draw_indicator(
-    terminal: &mut Terminal,
-    canvas: &mut Canvas,
-    point: Point,
-    cell: &Cell,
-    color: Color,
-) {
+fn draw_indicator(terminal: &mut Terminal, canvas: &mut Canvas, point: Point, color: Color) {
     terminal.set_cursor(point);
     terminal.set_background_color(color.invert());
     terminal.write(" ");
@@ -61,19 +51,15 @@ fn draw_indicator(
 
     // Clear that same spot for the next flush
     terminal.reset_colors();
-    terminal.set_cursor(point);
-    if cell.upper_block.is_some() || cell.lower_block.is_some() {
-        canvas.redraw_cell(&cell.clone());
-    } else {
-        terminal.write(" ");
-    }
+    canvas.redraw_cell(canvas.canvas_point(point));
 }
 
-fn get_cell_and_color(canvas: &mut Canvas, point: Point) -> (Cell, Color) {
-    let cell = canvas.get_cell(Point {
-        y: point.y * 2,
-        ..point
-    });
-    let color = cell.upper_block.or(cell.lower_block).unwrap_or_default();
-    (cell.clone(), color)
+/// Returns the color seen at the given point of the terminal.
+fn get_color(canvas: &mut Canvas, point: Point) -> Color {
+    let point = canvas.canvas_point(point);
+    if !canvas.contains(point) {
+        return Color::default();
+    }
+    let cell = canvas.composite_cell(point);
+    cell.upper_block.or(cell.lower_block).unwrap_or_default()
 }
@@ -54,16 +54,14 @@ pub fn handle(event: &Event, terminal: &mut Terminal, input_field: &mut input::F
             }
             _ => return false,
         },
-        Event::Mouse(MouseEvent { kind, point: _ }) => {
-            match kind {
-                EventKind::Release(_) => {
-                    terminal.hide_cursor();
+        Event::Mouse(MouseEvent {
+            kind: EventKind::Release(_),
+            point: _,
+        }) => {
+            terminal.hide_cursor();
 
-                    // We need to have this event available for the palette event handler as well
-                    return false;
-                }
-                _ => return false,
-            }
+            // We need to have this event available for the palette event handler as well
+            return false;
         }
         _ => {
             return false;
@@ -1,8 +1,8 @@
 //! Movement of the drawing point using the WASD and arrow keys.
 
-use crate::terminal::event::{Event, KeyEvent};
+use crate::terminal::event::Event;
 
-pub fn handle(event: &Event) -> bool {
+pub fn handle(_event: &Event) -> bool {
     false
     //match event {
     //    Event::Key(key) => match key {
@@ -0,0 +1,168 @@
+//! Previews of what a tool would draw. They are drawn on the secondary canvas and shown over the primary canvas
+//! so that the primary canvas is only changed once the tool is done.
+
+use crate::{
+    canvas::{Canvas, Cell},
+    util::Point,
+};
+
+#[derive(Default)]
+pub struct Preview {
+    /// The points of the cells the preview covers.
+    points: Vec<Point>,
+    /// What the secondary canvas had at the points before if it's put back when the preview is cleared.
+    covered: Vec<Cell>,
+}
+
+impl Preview {
+    /// Replaces the preview with what the closure draws on the secondary canvas.
+    pub fn draw(
+        &mut self,
+        primary_canvas: &mut Canvas,
+        secondary_canvas: &mut Canvas,
+        draw: impl FnOnce(&mut Canvas),
+    ) {
+        self.clear(primary_canvas, secondary_canvas);
+        // The changes tell what was drawn
+        secondary_canvas.record_changes();
+        draw(secondary_canvas);
+        self.points = secondary_canvas.take_changed_points();
+        for point in &self.points {
+            secondary_canvas.redraw_cell_over(*point, primary_canvas);
+        }
+    }
+
+    /// Replaces the preview with the blocks the closure draws on the secondary canvas
+    /// in the inverted colors of the primary canvas's blocks below them so that they can be seen on anything.
+    ///
+    /// Other previews it covers are put back when it's cleared, so it has to be cleared
+    /// before anything else is drawn on the secondary canvas.
+    pub fn draw_inverted(
+        &mut self,
+        primary_canvas: &mut Canvas,
+        secondary_canvas: &mut Canvas,
+        draw: impl FnOnce(&mut Canvas),
+    ) {
+        self.clear(primary_canvas, secondary_canvas);
+        secondary_canvas.record_changes();
+        draw(secondary_canvas);
+        let (points, covered) = secondary_canvas.take_changed_cells().into_iter().unzip();
+        self.points = points;
+        self.covered = covered;
+        for point in &self.points {
+            let lower_point = Point {
+                y: point.y + 1,
+                ..*point
+            };
+            for &point in &[*point, lower_point] {
+                if secondary_canvas.get_block(point).is_some() && primary_canvas.contains(point) {
+                    let color = primary_canvas.composite_block(point).unwrap_or_default();
+                    secondary_canvas.block(point, color.invert());
+                }
+            }
+            secondary_canvas.redraw_cell_over(*point, primary_canvas);
+        }
+        secondary_canvas.take_changes();
+    }
+
+    /// Removes the preview and shows the primary canvas's cells below it again.
+    pub fn clear(&mut self, primary_canvas: &mut Canvas, secondary_canvas: &mut Canvas) {
+        let covered = std::mem::take(&mut self.covered);
+        for (index, point) in self.points.drain(..).enumerate() {
+            // The canvas might have been resized since
+            if !secondary_canvas.contains(point) {
+                continue;
+            }
+            match covered.get(index) {
+                Some(cell) => {
+                    secondary_canvas.set_cell(point, cell.clone());
+                    secondary_canvas.redraw_cell_over(point, primary_canvas);
+                }
+                None => {
+                    secondary_canvas.set_cell(point, Cell::default());
+                    // Previews on other layers of the secondary canvas are shown again too
+                    secondary_canvas.redraw_cell_over(point, primary_canvas);
+                }
+            }
+        }
+        secondary_canvas.take_changes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::tools::Tool,
+        util::{Color, Size},
+    };
+
+    #[test]
+    fn test_preview() {
+        let size = Size {
+            width: 10,
+            height: 5,
+        };
+        let mut primary_canvas = Canvas::new(size.clone());
+        let mut secondary_canvas = Canvas::new(size);
+        let mut preview = Preview::default();
+        let start = Point { x: 0, y: 0 };
+        let end = Point { x: 9, y: 9 };
+
+        preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            Tool::Line.draw_shape(canvas, start, end, Color::Red, None, 1);
+        });
+        assert_eq!(secondary_canvas.get_block(end), Some(Color::Red));
+        assert_eq!(primary_canvas.get_block(end), None);
+
+        preview.clear(&mut primary_canvas, &mut secondary_canvas);
+        assert_eq!(secondary_canvas.get_block(start), None);
+        assert_eq!(secondary_canvas.get_block(end), None);
+
+        primary_canvas.block(start, Color::White);
+        preview.draw_inverted(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            Tool::Brush.draw_footprint(canvas, start, 1);
+        });
+        assert_eq!(secondary_canvas.get_block(start), Some(Color::Black));
+
+        // Other previews below it are put back
+        let mut other_preview = Preview::default();
+        other_preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            canvas.block(end, Color::Red);
+        });
+        preview.draw_inverted(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            Tool::Brush.draw_footprint(canvas, end, 2);
+        });
+        assert_ne!(secondary_canvas.get_block(end), Some(Color::Red));
+        preview.clear(&mut primary_canvas, &mut secondary_canvas);
+        assert_eq!(secondary_canvas.get_block(end), Some(Color::Red));
+        assert_eq!(secondary_canvas.get_block(start), None);
+    }
+
+    #[test]
+    fn test_previews_on_layers() {
+        let size = Size {
+            width: 10,
+            height: 5,
+        };
+        let mut primary_canvas = Canvas::new(size.clone());
+        let mut secondary_canvas = Canvas::new(size);
+        secondary_canvas.add_layer();
+        let point = Point { x: 3, y: 3 };
+
+        // Clearing a preview doesn't remove the preview on the layer below
+        secondary_canvas.select_layer(0);
+        let mut lower_preview = Preview::default();
+        lower_preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            canvas.block(point, Color::Red);
+        });
+        secondary_canvas.select_layer(1);
+        let mut upper_preview = Preview::default();
+        upper_preview.draw(&mut primary_canvas, &mut secondary_canvas, |canvas| {
+            canvas.block(point, Color::Blue);
+        });
+        assert_eq!(secondary_canvas.composite_block(point), Some(Color::Blue));
+        upper_preview.clear(&mut primary_canvas, &mut secondary_canvas);
+        assert_eq!(secondary_canvas.composite_block(point), Some(Color::Red));
+    }
+}
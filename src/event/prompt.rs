@@ -0,0 +1,77 @@
+//! A dialog in the middle of the terminal that asks for a line of text.
+
+use crate::{
+    input, palette,
+    terminal::{
+        event::{Event, KeyEvent},
+        Terminal, SIZE,
+    },
+    util::{Color, Point, Size},
+};
+
+const HINT: &str = "Enter: OK, Esc: cancel";
+
+/// Shows the dialog and returns the text entered or `None` if it was cancelled.
+/// The caller is responsible for redrawing whatever was behind the dialog.
+pub fn handle(terminal: &mut Terminal, title: &str, input: String) -> Option<String> {
+    let size = Size {
+        width: palette::SIZE.width,
+        height: 3,
+    };
+    let point = terminal.get_centered_border_point(&size);
+
+    terminal.set_foreground_color(Color::White);
+    terminal.set_background_color(Color::DarkGray);
+    for index in 0..size.height {
+        terminal.set_cursor(Point {
+            y: point.y + index,
+            ..point
+        });
+        terminal.write(&" ".repeat(size.width as usize));
+    }
+    // Titles that are too long are cut off at the edge of the dialog
+    let title = title.chars().take(size.width as usize).collect::<String>();
+    let centered_x = |length: usize| (point.x + size.width / 2).saturating_sub(length as SIZE / 2);
+    terminal.set_cursor(Point {
+        x: centered_x(title.chars().count()),
+        ..point
+    });
+    terminal.write(&title);
+    terminal.set_cursor(Point {
+        x: centered_x(HINT.len()),
+        y: point.y + 2,
+    });
+    terminal.write(HINT);
+
+    let mut input_field = input::Field::new(
+        Point {
+            x: point.x + 1,
+            y: point.y + 1,
+        },
+        input,
+    );
+    input_field.update();
+    input_field.redraw(terminal);
+    terminal.show_cursor();
+    terminal.flush();
+
+    let mut text = None;
+    while let Some(event) = terminal.read_event() {
+        if super::input::handle(&event, terminal, &mut input_field) {
+            continue;
+        }
+
+        match event {
+            Event::Key(KeyEvent::Enter) if !input_field.input().is_empty() => {
+                text = Some(input_field.input().to_string());
+                break;
+            }
+            Event::Key(KeyEvent::Esc) => break,
+            _ => {}
+        }
+    }
+
+    terminal.hide_cursor();
+    terminal.reset_colors();
+    text
+}
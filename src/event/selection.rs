@@ -0,0 +1,289 @@
+//! The selection tool. An area is selected by dragging and can then be moved, copied, cut, pasted and deleted.
+//! The selected area is outlined with marching ants drawn as a preview on the secondary canvas.
+
+use super::{finish_shape, preview::Preview, undo_redo::UndoRedoBuffer, State};
+use crate::{
+    canvas::{
+        selection::{self, Clip},
+        tools::Tool,
+        transform::Transform,
+        Canvas,
+    },
+    terminal::{
+        event::{Event, EventKind, KeyEvent, KeyModifier, MouseButton, MouseEvent},
+        Terminal, SIZE,
+    },
+    util::{Color, Point},
+};
+
+#[derive(Default)]
+pub struct Selection {
+    /// The upper left and lower right point of the selected area.
+    area: Option<(Point, Point)>,
+    /// Where the mouse button was pressed to select or move.
+    start: Option<Point>,
+    /// What is being moved and whether the original stays where it is.
+    moving: Option<(Clip, bool)>,
+    clipboard: Option<Clip>,
+    /// This advances with every event so that the ants march.
+    phase: usize,
+    preview: Preview,
+}
+
+impl Selection {
+    /// Returns the selected area unless it's being selected or moved.
+    pub fn area(&self) -> Option<(Point, Point)> {
+        self.area.filter(|_| self.start.is_none())
+    }
+
+    /// Returns whether an area is being selected or moved.
+    pub fn is_dragging(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Draws the outline of the selected area again, e.g. after something was drawn over it.
+    pub fn redraw(&mut self, primary_canvas: &mut Canvas, secondary_canvas: &mut Canvas) {
+        if let Some(area) = self.area() {
+            self.draw(primary_canvas, secondary_canvas, area.0);
+        }
+    }
+
+    /// Drops the selection and puts back what was being moved.
+    fn deselect(&mut self, primary_canvas: &mut Canvas, secondary_canvas: &mut Canvas) {
+        if let (Some((clip, duplicate)), Some(area)) = (self.moving.take(), self.area) {
+            if !duplicate {
+                primary_canvas.paste(&clip, area.0);
+            }
+        }
+        self.area = None;
+        self.start = None;
+        self.preview.clear(primary_canvas, secondary_canvas);
+    }
+
+    /// Transforms what is selected and returns whether something is selected.
+    pub fn transform(
+        &mut self,
+        primary_canvas: &mut Canvas,
+        secondary_canvas: &mut Canvas,
+        transform: Transform,
+    ) -> bool {
+        let area = match self.area {
+            Some(area) if self.start.is_none() => area,
+            _ => return false,
+        };
+        let clip = primary_canvas.copy(area).transform(transform);
+        primary_canvas.delete(area);
+        primary_canvas.paste(&clip, area.0);
+        self.area = Some(clip_area(&clip, area.0));
+        self.draw(primary_canvas, secondary_canvas, area.0);
+        true
+    }
+
+    /// Draws what is being moved and the outline of the selected area or the area being selected.
+    fn draw(&mut self, primary_canvas: &mut Canvas, secondary_canvas: &mut Canvas, point: Point) {
+        self.phase = self.phase.wrapping_add(1);
+        let (area, clip) = match (self.area, self.start, &self.moving) {
+            (Some(area), Some(start), Some((clip, _))) => (moved(area, start, point), Some(clip)),
+            (_, Some(start), None) => (selection::area(start, point), None),
+            (Some(area), _, _) => (area, None),
+            (None, _, _) => {
+                self.preview.clear(primary_canvas, secondary_canvas);
+                return;
+            }
+        };
+        let phase = self.phase;
+        self.preview
+            .draw(primary_canvas, secondary_canvas, |canvas| {
+                if let Some(clip) = clip {
+                    canvas.paste(clip, area.0);
+                }
+                draw_ants(canvas, area, phase);
+            });
+    }
+}
+
+/// Returns the area the clip covers when it's pasted at the point.
+fn clip_area(clip: &Clip, point: Point) -> (Point, Point) {
+    (
+        point,
+        Point {
+            x: point.x.saturating_add(clip.width - 1),
+            y: point.y.saturating_add(clip.height - 1),
+        },
+    )
+}
+
+/// Returns the area moved by how far the point is from the start.
+fn moved((upper_left, lower_right): (Point, Point), start: Point, point: Point) -> (Point, Point) {
+    let offset = |coordinate: SIZE, from: SIZE, to: SIZE| {
+        (coordinate as i32 + to as i32 - from as i32).clamp(0, SIZE::MAX as i32) as SIZE
+    };
+    let moved_upper_left = Point {
+        x: offset(upper_left.x, start.x, point.x),
+        y: offset(upper_left.y, start.y, point.y),
+    };
+    let moved_lower_right = Point {
+        x: moved_upper_left
+            .x
+            .saturating_add(lower_right.x - upper_left.x),
+        y: moved_upper_left
+            .y
+            .saturating_add(lower_right.y - upper_left.y),
+    };
+    (moved_upper_left, moved_lower_right)
+}
+
+/// Draws the edge of the area in alternating black and white stripes that are shifted by the phase.
+fn draw_ants(canvas: &mut Canvas, (upper_left, lower_right): (Point, Point), phase: usize) {
+    let mut ant = |x: SIZE, y: SIZE| {
+        let color = if (x as usize + y as usize + phase) % 4 < 2 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        canvas.block(Point { x, y }, color);
+    };
+    for x in upper_left.x..=lower_right.x {
+        ant(x, upper_left.y);
+        ant(x, lower_right.y);
+    }
+    for y in upper_left.y..=lower_right.y {
+        ant(upper_left.x, y);
+        ant(lower_right.x, y);
+    }
+}
+
+/// Handles the events of the selection tool and the keys for the clipboard.
+/// Returns whether the event was consumed.
+#[allow(clippy::too_many_arguments)]
+pub fn handle(
+    event: &Event,
+    terminal: &mut Terminal,
+    state: &mut State,
+    primary_canvas: &mut Canvas,
+    secondary_canvas: &mut Canvas,
+    selection: &mut Selection,
+    preview: &mut Preview,
+    undo_redo_buffer: &mut UndoRedoBuffer,
+) -> bool {
+    // The gradient tool fills the selection so it's kept
+    let keeps_selection = matches!(state.tool, Tool::Select | Tool::Gradient(_));
+    if !keeps_selection && (selection.area.is_some() || selection.start.is_some()) {
+        selection.deselect(primary_canvas, secondary_canvas);
+        terminal.flush();
+    }
+
+    match event {
+        Event::Mouse(MouseEvent { kind, point }) if state.tool == Tool::Select => {
+            let point = primary_canvas.canvas_point(*point);
+            match kind {
+                EventKind::Press(button @ (MouseButton::Left | MouseButton::Right)) => {
+                    // Moving is one step that can be undone
+                    undo_redo_buffer.start_stroke();
+                    match selection.area {
+                        Some(area) if selection::area_contains(area, point) => {
+                            let clip = primary_canvas.copy(area);
+                            // Moving with the right mouse button moves a copy
+                            let duplicate = matches!(button, MouseButton::Right);
+                            if !duplicate {
+                                primary_canvas.delete(area);
+                            }
+                            selection.moving = Some((clip, duplicate));
+                        }
+                        _ => selection.area = None,
+                    }
+                    selection.start = Some(point);
+                }
+                EventKind::Drag(MouseButton::Left | MouseButton::Right) => {}
+                EventKind::Release(MouseButton::Left | MouseButton::Right) => {
+                    if let Some(start) = selection.start.take() {
+                        match (selection.moving.take(), selection.area) {
+                            (Some((clip, _)), Some(area)) => {
+                                let area = moved(area, start, point);
+                                primary_canvas.paste(&clip, area.0);
+                                selection.area = Some(area);
+                            }
+                            // Clicking without dragging deselects
+                            _ if start != point => {
+                                selection.area = Some(selection::area(start, point))
+                            }
+                            _ => {}
+                        }
+                    }
+                    undo_redo_buffer.end_stroke(primary_canvas);
+                }
+                _ => {
+                    // The main loop handles this as well but the ants keep marching
+                    selection.draw(primary_canvas, secondary_canvas, point);
+                    terminal.flush();
+                    return false;
+                }
+            }
+            selection.draw(primary_canvas, secondary_canvas, point);
+            terminal.flush();
+            true
+        }
+        Event::Key(KeyEvent::Char(key @ ('c' | 'x'), Some(KeyModifier::Control))) => {
+            // Without a selection, Ctrl+C exits
+            let area = match selection.area {
+                Some(area) if selection.start.is_none() => area,
+                _ => return false,
+            };
+            selection.clipboard = Some(primary_canvas.copy(area));
+            if *key == 'x' {
+                primary_canvas.delete(area);
+                selection.preview.clear(primary_canvas, secondary_canvas);
+                selection.area = None;
+            }
+            terminal.flush();
+            true
+        }
+        Event::Key(KeyEvent::Char('v', Some(KeyModifier::Control))) => {
+            let clip = match &selection.clipboard {
+                Some(clip) if selection.start.is_none() => clip.clone(),
+                _ => return false,
+            };
+            let point = state
+                .lasting_last_point
+                .unwrap_or_else(|| primary_canvas.viewport());
+            finish_shape(state, preview, primary_canvas, secondary_canvas);
+            primary_canvas.paste(&clip, point);
+            selection.area = Some(clip_area(&clip, point));
+            state.tool = Tool::Select;
+            selection.draw(primary_canvas, secondary_canvas, point);
+            terminal.flush();
+            true
+        }
+        Event::Key(KeyEvent::Delete | KeyEvent::Backspace(None)) => {
+            let area = match selection.area {
+                Some(area) if selection.start.is_none() => area,
+                _ => return false,
+            };
+            primary_canvas.delete(area);
+            selection.preview.clear(primary_canvas, secondary_canvas);
+            selection.area = None;
+            terminal.flush();
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moved() {
+        let area = (Point { x: 2, y: 3 }, Point { x: 4, y: 8 });
+        assert_eq!(
+            moved(area, Point { x: 3, y: 3 }, Point { x: 5, y: 2 }),
+            (Point { x: 4, y: 2 }, Point { x: 6, y: 7 })
+        );
+        // The area can't be moved past the upper left corner
+        assert_eq!(
+            moved(area, Point { x: 3, y: 3 }, Point { x: 0, y: 0 }),
+            (Point { x: 0, y: 0 }, Point { x: 2, y: 5 })
+        );
+    }
+}
@@ -1,70 +1,72 @@
 use crate::{
-    canvas::{tools, Canvas},
+    canvas::{changes::Change, Canvas},
     terminal::{
         event::{Event, KeyEvent},
-        Terminal, SIZE,
+        Terminal,
     },
-    util::{Color, Point},
 };
 
-pub struct Operation {
-    pub tool: tools::Tool,
-    pub start: Point,
-    pub end: Option<Point>,
-    pub color: Color,
-    pub size: SIZE,
-}
-
 pub struct UndoRedoBuffer {
-    buffer: Vec<Operation>,
+    /// Every entry holds the changes of one step in the order they were made.
+    buffer: Vec<Vec<Change>>,
     cursor: usize,
+    /// Whether a stroke is in progress. All changes of a stroke are one step.
+    stroke: bool,
 }
 
 impl UndoRedoBuffer {
-    pub fn new() -> Self {
+    /// Creates an empty buffer and starts recording the changes of the canvas.
+    pub fn new(canvas: &mut Canvas) -> Self {
+        canvas.record_changes();
         Self {
-            buffer: Vec::<Operation>::new(),
+            buffer: Vec::<Vec<Change>>::new(),
             cursor: 0,
+            stroke: false,
         }
     }
 
-    pub fn push(&mut self, operation: Operation) {
+    /// Makes the changes of the canvas since the last commit one step that can be undone.
+    /// Nothing is committed while a stroke is in progress.
+    pub fn commit(&mut self, canvas: &mut Canvas) {
+        if self.stroke {
+            return;
+        }
+        let changes = canvas.take_changes();
+        if changes.is_empty() {
+            return;
+        }
         if self.cursor != self.buffer.len() {
             self.buffer.truncate(self.cursor);
         }
-        self.buffer.push(operation);
+        self.buffer.push(changes);
         self.cursor += 1;
     }
 
-    fn undo(&mut self, canvas: &mut Canvas, terminal: &mut Terminal) {
+    /// Starts a stroke, e.g. when a mouse button is pressed.
+    pub fn start_stroke(&mut self) {
+        self.stroke = true;
+    }
+
+    /// Ends the stroke, if there is one, and commits all of its changes as one step.
+    pub fn end_stroke(&mut self, canvas: &mut Canvas) {
+        self.stroke = false;
+        self.commit(canvas);
+    }
+
+    fn undo(&mut self, canvas: &mut Canvas) {
         if self.cursor == 0 {
             return;
         }
         self.cursor -= 1;
-        self.draw(canvas, terminal);
+        canvas.revert_changes(&self.buffer[self.cursor]);
     }
 
-    fn redo(&mut self, canvas: &mut Canvas, terminal: &mut Terminal) {
+    fn redo(&mut self, canvas: &mut Canvas) {
         if self.cursor == self.buffer.len() {
             return;
         }
+        canvas.apply_changes(&self.buffer[self.cursor]);
         self.cursor += 1;
-        self.draw(canvas, terminal);
-    }
-
-    fn draw(&mut self, canvas: &mut Canvas, terminal: &mut Terminal) {
-        canvas.clear();
-        terminal.clear();
-        for operation in &mut self.buffer[..self.cursor] {
-            operation.tool.draw(
-                canvas,
-                operation.start,
-                operation.end,
-                operation.color,
-                operation.size,
-            );
-        }
-        terminal.flush();
     }
 }
 
@@ -77,10 +79,12 @@ pub fn handle(
     if let Event::Key(key) = event {
         match key {
             KeyEvent::Char('z', _) | KeyEvent::Char('Z', _) => {
-                undo_redo_buffer.undo(canvas, terminal);
+                undo_redo_buffer.end_stroke(canvas);
+                undo_redo_buffer.undo(canvas);
             }
             KeyEvent::Char('y', _) | KeyEvent::Char('Y', _) => {
-                undo_redo_buffer.redo(canvas, terminal);
+                undo_redo_buffer.end_stroke(canvas);
+                undo_redo_buffer.redo(canvas);
             }
             _ => return false,
         }
@@ -90,3 +94,34 @@ pub fn handle(
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{Color, Point, Size};
+
+    #[test]
+    fn test_stroke() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 1,
+        });
+        let mut undo_redo_buffer = UndoRedoBuffer::new(&mut canvas);
+        canvas.block(Point { x: 0, y: 0 }, Color::Red);
+        undo_redo_buffer.commit(&mut canvas);
+
+        undo_redo_buffer.start_stroke();
+        for x in 0..3 {
+            canvas.block(Point { x, y: 1 }, Color::Blue);
+            undo_redo_buffer.commit(&mut canvas);
+        }
+        undo_redo_buffer.end_stroke(&mut canvas);
+        assert_eq!(undo_redo_buffer.buffer.len(), 2);
+
+        undo_redo_buffer.undo(&mut canvas);
+        assert_eq!(canvas.get_block(Point { x: 0, y: 0 }), Some(Color::Red));
+        assert!((0..3).all(|x| canvas.get_block(Point { x, y: 1 }).is_none()));
+        undo_redo_buffer.redo(&mut canvas);
+        assert!((0..3).all(|x| canvas.get_block(Point { x, y: 1 }) == Some(Color::Blue)));
+    }
+}
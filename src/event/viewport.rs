@@ -0,0 +1,78 @@
+//! Moving the viewport of the canvas using the arrow keys or by dragging with the middle mouse button.
+
+use crate::{
+    canvas::Canvas,
+    terminal::{
+        event::{Event, EventKind, KeyEvent, MouseButton, MouseEvent},
+        Terminal, SIZE,
+    },
+    util::Point,
+};
+
+#[derive(Default)]
+pub struct Panning {
+    /// The last point of the mouse while the middle mouse button is held down.
+    last_point: Option<Point>,
+    /// Whether the viewport was moved since the middle mouse button was pressed.
+    moved: bool,
+}
+
+pub fn handle(
+    event: &Event,
+    terminal: &mut Terminal,
+    canvas: &mut Canvas,
+    panning: &mut Panning,
+) -> bool {
+    // The distance to move the viewport by in cells
+    let (x, y): (i32, i32) = match event {
+        Event::Key(KeyEvent::Up) => (0, -1),
+        Event::Key(KeyEvent::Down) => (0, 1),
+        Event::Key(KeyEvent::Left(None)) => (-1, 0),
+        Event::Key(KeyEvent::Right(None)) => (1, 0),
+        Event::Mouse(MouseEvent {
+            kind: EventKind::Press(MouseButton::Middle),
+            point,
+        }) => {
+            panning.last_point = Some(*point);
+            panning.moved = false;
+            return true;
+        }
+        Event::Mouse(MouseEvent {
+            kind: EventKind::Drag(MouseButton::Middle),
+            point,
+        }) => {
+            let last_point = match panning.last_point.replace(*point) {
+                Some(last_point) => last_point,
+                None => return true,
+            };
+            panning.moved = true;
+            // The canvas follows the mouse
+            (
+                last_point.x as i32 - point.x as i32,
+                last_point.y as i32 - point.y as i32,
+            )
+        }
+        Event::Mouse(MouseEvent {
+            kind: EventKind::Release(MouseButton::Middle),
+            ..
+        }) => {
+            panning.last_point = None;
+            // A click without dragging is handled by the color picker
+            return panning.moved;
+        }
+        _ => return false,
+    };
+
+    let viewport = canvas.viewport();
+    let new_viewport = Point {
+        x: (viewport.x as i32 + x).clamp(0, SIZE::MAX as i32) as SIZE,
+        y: (viewport.y as i32 + y * 2).clamp(0, SIZE::MAX as i32) as SIZE,
+    };
+    canvas.set_viewport(new_viewport);
+    if canvas.viewport() != viewport {
+        terminal.clear();
+        canvas.redraw();
+        terminal.flush();
+    }
+    true
+}
@@ -0,0 +1,5 @@
+//! Reading and writing canvases from and to files.
+
+pub mod ansi;
+pub mod image;
+pub mod native;
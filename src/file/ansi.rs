@@ -0,0 +1,473 @@
+//! Exporting canvases as ANSI art: text made of half blocks colored using escape sequences
+//! that can be printed to a terminal, e.g. using `cat`.
+//! Importing ANSI art made with other programs or captured from a terminal is supported as well.
+
+use crate::{
+    canvas::{Canvas, Cell, MAX_SIZE},
+    terminal::{Terminal, SIZE},
+    util::{Color, Point, Size},
+};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Block characters drawn with the terminal's default foreground color are imported with this color.
+const DEFAULT_FOREGROUND_COLOR: Color = Color::White;
+
+pub fn export_ansi(canvas: &Canvas, path: &Path) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+    write_ansi(canvas, &mut writer)?;
+    writer.flush()
+}
+
+/// Writes the whole canvas as lines of text with all visible layers merged.
+/// The colors are reset at the end of every line.
+pub fn write_ansi(canvas: &Canvas, writer: &mut impl Write) -> io::Result<()> {
+    let size = canvas.size();
+    for row in 0..size.height {
+        let mut line = Line::default();
+        for x in 0..size.width {
+            line.push(&canvas.composite_cell(Point { x, y: row * 2 }));
+        }
+        line.reset();
+        writeln!(writer, "{}", line.text)?;
+    }
+
+    Ok(())
+}
+
+/// A line of text with the colors currently set.
+#[derive(Default)]
+struct Line {
+    text: String,
+    foreground_color: Option<Color>,
+    background_color: Option<Color>,
+}
+
+impl Line {
+    fn push(&mut self, cell: &Cell) {
+        let (character, foreground_color, background_color) = match cell {
+            Cell {
+                character: Some(character),
+                ..
+            } => (*character, None, None),
+            Cell {
+                upper_block: Some(upper_block_color),
+                lower_block,
+                ..
+            } => ('▀', Some(*upper_block_color), *lower_block),
+            Cell {
+                upper_block: None,
+                lower_block: Some(lower_block_color),
+                ..
+            } => ('▄', Some(*lower_block_color), None),
+            _ => (' ', None, None),
+        };
+
+        // Colors can only be unset by resetting both of them
+        if (foreground_color.is_none() && self.foreground_color.is_some())
+            || (background_color.is_none() && self.background_color.is_some())
+        {
+            self.reset();
+        }
+        if let Some(color) = foreground_color {
+            if self.foreground_color != foreground_color {
+                self.text += &Terminal::foreground_color_sequence(color);
+                self.foreground_color = foreground_color;
+            }
+        }
+        if let Some(color) = background_color {
+            if self.background_color != background_color {
+                self.text += &Terminal::background_color_sequence(color);
+                self.background_color = background_color;
+            }
+        }
+        self.text.push(character);
+    }
+
+    fn reset(&mut self) {
+        if self.foreground_color.is_some() || self.background_color.is_some() {
+            self.text += &Terminal::reset_colors_sequence();
+            self.foreground_color = None;
+            self.background_color = None;
+        }
+    }
+}
+
+pub fn import_ansi(canvas: &mut Canvas, path: &Path) -> io::Result<()> {
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    read_ansi(canvas, &mut reader)
+}
+
+/// Replaces the canvas's cells with the ANSI art read and resizes the canvas to fit it.
+/// Cells that don't fit into the largest possible canvas are discarded.
+pub fn read_ansi(canvas: &mut Canvas, reader: &mut impl Read) -> io::Result<()> {
+    let mut bytes = Vec::<u8>::new();
+    reader.read_to_end(&mut bytes)?;
+    let cells = parse(&decode(&bytes));
+
+    let width = cells.iter().map(|(x, _, _)| x + 1).max().unwrap_or(1);
+    let height = cells.iter().map(|(_, row, _)| row + 1).max().unwrap_or(1);
+    canvas.resize(Size {
+        width: width.min(MAX_SIZE.width as usize) as SIZE,
+        height: height.min(MAX_SIZE.height as usize) as SIZE,
+    });
+    let size = canvas.size();
+    canvas.clear();
+    for (x, row, cell) in cells {
+        if x < size.width as usize && row < size.height as usize {
+            canvas.set_cell(
+                Point {
+                    x: x as SIZE,
+                    y: row as SIZE * 2,
+                },
+                cell,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the text as UTF-8 or, if that fails, as code page 437 which is what most .ans files are encoded in.
+fn decode(bytes: &[u8]) -> String {
+    // The end of file character is followed by metadata
+    let bytes = bytes.split(|&byte| byte == 0x1A).next().unwrap_or_default();
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&byte| match byte {
+                0x00..=0x7F => byte as char,
+                0xB0 => '░',
+                0xB1 => '▒',
+                0xB2 => '▓',
+                0xDB => '█',
+                0xDC => '▄',
+                0xDF => '▀',
+                _ => '?',
+            })
+            .collect(),
+    }
+}
+
+/// Returns the x coordinate, the row and the cell of every cell that is not empty.
+fn parse(text: &str) -> Vec<(usize, usize, Cell)> {
+    let mut cells = Vec::<(usize, usize, Cell)>::new();
+    let (mut x, mut row): (usize, usize) = (0, 0);
+    // Where the cursor was saved
+    let mut saved_position = (0, 0);
+    let mut foreground_color: Option<Color> = None;
+    let mut background_color: Option<Color> = None;
+
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        let cell = match character {
+            '\x1b' => {
+                // Only Control Sequence Introducer sequences are of interest
+                if characters.peek() != Some(&'[') {
+                    continue;
+                }
+                characters.next();
+                let mut parameters = String::new();
+                let mut command = None;
+                for character in characters.by_ref() {
+                    if ('\x40'..='\x7E').contains(&character) {
+                        command = Some(character);
+                        break;
+                    }
+                    parameters.push(character);
+                }
+                match command {
+                    Some('m') => select_graphic_rendition(
+                        &parameters,
+                        &mut foreground_color,
+                        &mut background_color,
+                    ),
+                    // Cursor up, down, forward and back
+                    Some('A') => row = row.saturating_sub(count(&parameters)),
+                    Some('B') => row = row.saturating_add(count(&parameters)),
+                    Some('C') => x = x.saturating_add(count(&parameters)),
+                    Some('D') => x = x.saturating_sub(count(&parameters)),
+                    // Cursor position, starting at 1
+                    Some('H' | 'f') => {
+                        let mut parameters = parameters.split(';').map(count);
+                        row = parameters.next().unwrap_or(1) - 1;
+                        x = parameters.next().unwrap_or(1) - 1;
+                    }
+                    Some('s') => saved_position = (x, row),
+                    Some('u') => (x, row) = saved_position,
+                    _ => {}
+                }
+                // Cells beyond the largest possible canvas are discarded anyway
+                x = x.min(MAX_SIZE.width as usize);
+                row = row.min(MAX_SIZE.height as usize);
+                continue;
+            }
+            '\n' => {
+                x = 0;
+                row = (row + 1).min(MAX_SIZE.height as usize);
+                continue;
+            }
+            '\r' => {
+                x = 0;
+                continue;
+            }
+            '\t' => {
+                x = (x + 8 - x % 8).min(MAX_SIZE.width as usize);
+                continue;
+            }
+            _ if character.is_control() => continue,
+            '▀' => Cell {
+                upper_block: Some(foreground_color.unwrap_or(DEFAULT_FOREGROUND_COLOR)),
+                lower_block: background_color,
+                ..Default::default()
+            },
+            '▄' => Cell {
+                upper_block: background_color,
+                lower_block: Some(foreground_color.unwrap_or(DEFAULT_FOREGROUND_COLOR)),
+                ..Default::default()
+            },
+            '█' => Cell {
+                upper_block: Some(foreground_color.unwrap_or(DEFAULT_FOREGROUND_COLOR)),
+                lower_block: Some(foreground_color.unwrap_or(DEFAULT_FOREGROUND_COLOR)),
+                ..Default::default()
+            },
+            ' ' => Cell {
+                upper_block: background_color,
+                lower_block: background_color,
+                ..Default::default()
+            },
+            _ => Cell {
+                character: Some(character),
+                ..Default::default()
+            },
+        };
+        if cell != Cell::default() {
+            cells.push((x, row, cell));
+        }
+        x = (x + 1).min(MAX_SIZE.width as usize);
+    }
+
+    cells
+}
+
+/// Returns the number a cursor movement's parameter gives, which is 1 if it's omitted or 0.
+/// Numbers too large to be parsed are as large as possible.
+fn count(parameter: &str) -> usize {
+    match parameter.parse::<usize>() {
+        Ok(0) => 1,
+        Ok(count) => count,
+        Err(_) if !parameter.is_empty() && parameter.bytes().all(|byte| byte.is_ascii_digit()) => {
+            usize::MAX
+        }
+        Err(_) => 1,
+    }
+}
+
+/// Applies the parameters of a Select Graphic Rendition sequence to the colors.
+/// Parameters other than colors are ignored.
+fn select_graphic_rendition(
+    parameters: &str,
+    foreground_color: &mut Option<Color>,
+    background_color: &mut Option<Color>,
+) {
+    // An omitted parameter is 0
+    let parameters: Vec<u8> = parameters
+        .split(';')
+        .map(|parameter| {
+            if parameter.is_empty() {
+                0
+            } else {
+                parameter.parse().unwrap_or(u8::MAX)
+            }
+        })
+        .collect();
+
+    let mut parameters = parameters.iter().copied();
+    while let Some(parameter) = parameters.next() {
+        match parameter {
+            0 => {
+                *foreground_color = None;
+                *background_color = None;
+            }
+            30..=37 => *foreground_color = Some(Color::from_ansi_index(parameter - 30)),
+            38 => *foreground_color = extended_color(&mut parameters),
+            39 => *foreground_color = None,
+            40..=47 => *background_color = Some(Color::from_ansi_index(parameter - 40)),
+            48 => *background_color = extended_color(&mut parameters),
+            49 => *background_color = None,
+            90..=97 => *foreground_color = Some(Color::from_ansi_index(parameter - 90 + 8)),
+            100..=107 => *background_color = Some(Color::from_ansi_index(parameter - 100 + 8)),
+            _ => {}
+        }
+    }
+}
+
+/// Parses the parameters following a 38 or 48 parameter: `5;n` for 8-bit colors and `2;r;g;b` for RGB colors.
+fn extended_color(parameters: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match parameters.next()? {
+        5 => Some(Color::from_ansi_index(parameters.next()?)),
+        2 => Some(Color::Rgb {
+            r: parameters.next()?,
+            g: parameters.next()?,
+            b: parameters.next()?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ansi() {
+        let mut canvas = Canvas::new(Size {
+            width: 6,
+            height: 3,
+        });
+
+        canvas.set_cell(
+            Point { x: 2, y: 2 },
+            Cell {
+                upper_block: Some(Color::Red),
+                lower_block: Some(Color::ByteColor(100)),
+                ..Default::default()
+            },
+        );
+        canvas.set_cell(
+            Point { x: 3, y: 2 },
+            Cell {
+                upper_block: Some(Color::Red),
+                ..Default::default()
+            },
+        );
+        canvas.set_cell(
+            Point { x: 5, y: 3 },
+            Cell {
+                lower_block: Some(Color::Rgb { r: 1, g: 2, b: 3 }),
+                ..Default::default()
+            },
+        );
+        canvas.write_character(Point { x: 3, y: 4 }, 'A');
+
+        let mut bytes = Vec::<u8>::new();
+        write_ansi(&canvas, &mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            concat!(
+                "      \n",
+                "  \x1b[38;5;9m\x1b[48;5;100m▀\x1b[0m\x1b[38;5;9m▀\x1b[0m \x1b[38;2;1;2;3m▄\x1b[0m\n",
+                "   A  \n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_ansi() {
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        let text = concat!(
+            "\x1b[31m▀\x1b[44m▄\x1b[0m█ \x1b[101m \x1b[0mA\r\n",
+            "\x1b[38;5;100;48;2;1;2;3m▀\x1b[2C\x1b[1;93m▄\x1b[K",
+        );
+        read_ansi(&mut canvas, &mut text.as_bytes()).unwrap();
+
+        let cell = |x, row: SIZE| {
+            let cell = canvas.get_cell(Point { x, y: row * 2 });
+            (cell.upper_block, cell.lower_block, cell.character)
+        };
+        let rgb = Color::Rgb { r: 1, g: 2, b: 3 };
+        assert_eq!(cell(0, 0), (Some(Color::DarkRed), None, None));
+        assert_eq!(
+            cell(1, 0),
+            (Some(Color::DarkBlue), Some(Color::DarkRed), None)
+        );
+        assert_eq!(cell(2, 0), (Some(Color::White), Some(Color::White), None));
+        assert_eq!(cell(3, 0), (None, None, None));
+        assert_eq!(cell(4, 0), (Some(Color::Red), Some(Color::Red), None));
+        assert_eq!(cell(5, 0), (None, None, Some('A')));
+        assert_eq!(cell(0, 1), (Some(Color::ByteColor(100)), Some(rgb), None));
+        assert_eq!(cell(3, 1), (Some(rgb), Some(Color::Yellow), None));
+    }
+
+    #[test]
+    fn test_read_ansi_code_page_437() {
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        read_ansi(&mut canvas, &mut &b"\x1b[32m\xDF\xDB\x1A\xDB"[..]).unwrap();
+        assert_eq!(
+            canvas.get_block(Point { x: 0, y: 0 }),
+            Some(Color::DarkGreen)
+        );
+        assert_eq!(canvas.get_block(Point { x: 0, y: 1 }), None);
+        assert_eq!(
+            canvas.get_block(Point { x: 1, y: 1 }),
+            Some(Color::DarkGreen)
+        );
+        // Metadata after the end of file character is ignored
+        assert!(!canvas.contains(Point { x: 2, y: 0 }));
+    }
+
+    #[test]
+    fn test_read_ansi_cursor_movement() {
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        let text = "\x1b[3;4HA\x1b[s\x1b[2AB\x1b[BC\x1b[3DD\x1b[uE\r\x1b[BF";
+        read_ansi(&mut canvas, &mut text.as_bytes()).unwrap();
+
+        let character = |x, row: SIZE| canvas.get_cell(Point { x, y: row * 2 }).character;
+        assert_eq!(character(3, 2), Some('A'));
+        assert_eq!(character(4, 0), Some('B'));
+        assert_eq!(character(5, 1), Some('C'));
+        assert_eq!(character(3, 1), Some('D'));
+        assert_eq!(character(4, 2), Some('E'));
+        assert_eq!(character(0, 3), Some('F'));
+
+        // Moving past the largest possible canvas doesn't overflow
+        let text = "\x1b[99999999999999999999CG";
+        read_ansi(&mut canvas, &mut text.as_bytes()).unwrap();
+        assert_eq!(canvas.size().width, MAX_SIZE.width);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        canvas.block(Point { x: 0, y: 0 }, Color::Red);
+        canvas.block(Point { x: 0, y: 1 }, Color::ByteColor(100));
+        canvas.block(Point { x: 2, y: 1 }, Color::Rgb { r: 1, g: 2, b: 3 });
+        canvas.block(Point { x: 1, y: 2 }, Color::Gray);
+        canvas.write_character(Point { x: 2, y: 2 }, 'A');
+
+        let mut bytes = Vec::<u8>::new();
+        write_ansi(&canvas, &mut bytes).unwrap();
+        let mut imported_canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        read_ansi(&mut imported_canvas, &mut bytes.as_slice()).unwrap();
+
+        for y in 0..4 {
+            for x in 0..3 {
+                let point = Point { x, y };
+                assert_eq!(canvas.get_block(point), imported_canvas.get_block(point));
+                assert_eq!(
+                    canvas.get_cell(point).character,
+                    imported_canvas.get_cell(point).character
+                );
+            }
+        }
+    }
+}
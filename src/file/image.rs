@@ -0,0 +1,284 @@
+//! Exporting canvases as PNG images and importing PNG images into canvases.
+//!
+//! Every block is one pixel so a cell is two pixels high. Transparent pixels are used where there is no block.
+
+use crate::{
+    canvas::{Canvas, MAX_SIZE},
+    terminal::SIZE,
+    util::{Color, Point, Size},
+};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Pixels with an alpha value below this are imported as no block.
+const ALPHA_THRESHOLD: u8 = u8::MAX / 2;
+
+pub fn export_png(canvas: &Canvas, path: &Path) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+    write_png(canvas, &mut writer)?;
+    writer.flush()
+}
+
+/// Writes the whole canvas as a PNG image with all visible layers merged.
+pub fn write_png(canvas: &Canvas, writer: &mut impl Write) -> io::Result<()> {
+    let size = canvas.size();
+    let width = size.width as u32;
+    let height = size.height as u32 * 2;
+
+    let mut data = Vec::<u8>::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as SIZE {
+        for x in 0..width as SIZE {
+            match canvas.composite_block(Point { x, y }) {
+                Some(color) => {
+                    let (r, g, b) = color.to_rgb();
+                    data.extend_from_slice(&[r, g, b, u8::MAX]);
+                }
+                None => data.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+pub fn import_png(
+    canvas: &mut Canvas,
+    path: &Path,
+    size: &Size,
+    truecolor: bool,
+) -> io::Result<()> {
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    read_png(canvas, &mut reader, size, truecolor)
+}
+
+/// Replaces the canvas's cells with the image, scaled down to fit into the given size in cells,
+/// and resizes the canvas to the image's size.
+///
+/// If `truecolor` is not set, the colors are reduced to the closest colors of the xterm 256-color table.
+pub fn read_png(
+    canvas: &mut Canvas,
+    reader: &mut impl Read,
+    size: &Size,
+    truecolor: bool,
+) -> io::Result<()> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    let data = &data[..info.buffer_size()];
+    let pixels: Vec<[u8; 4]> = match info.color_type {
+        png::ColorType::Grayscale => data.iter().map(|&v| [v, v, v, u8::MAX]).collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+            .collect(),
+        png::ColorType::Rgba => data
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "indexed image was not expanded",
+            ))
+        }
+    };
+
+    let max_width = size.width.clamp(1, MAX_SIZE.width) as u32;
+    let max_height = size.height.clamp(1, MAX_SIZE.height) as u32 * 2;
+    let scale = (info.width as f64 / max_width as f64)
+        .max(info.height as f64 / max_height as f64)
+        .max(1.0);
+    let width = ((info.width as f64 / scale) as u32).clamp(1, max_width);
+    let height = ((info.height as f64 / scale) as u32).clamp(1, max_height);
+
+    // Scale down and convert everything first so that the canvas is left untouched if something fails
+    let mut blocks = Vec::<(Point, Color)>::new();
+    for y in 0..height {
+        for x in 0..width {
+            let area = (
+                x * info.width / width,
+                (x + 1) * info.width / width,
+                y * info.height / height,
+                (y + 1) * info.height / height,
+            );
+            if let Some((r, g, b)) = average_color(&pixels, info.width, area) {
+                let color = if truecolor {
+                    Color::Rgb { r, g, b }
+                } else {
+                    Color::nearest_ansi_color(r, g, b)
+                };
+                let point = Point {
+                    x: x as SIZE,
+                    y: y as SIZE,
+                };
+                blocks.push((point, color));
+            }
+        }
+    }
+
+    canvas.resize(Size {
+        width: width as SIZE,
+        height: height.div_ceil(2) as SIZE,
+    });
+    canvas.clear();
+    for (point, color) in blocks {
+        let mut cell = canvas.get_cell(point).clone();
+        if point.y % 2 == 0 {
+            cell.upper_block = Some(color);
+        } else {
+            cell.lower_block = Some(color);
+        }
+        canvas.set_cell(point, cell);
+    }
+
+    Ok(())
+}
+
+/// Returns the average color of the pixels in the area given as start x, end x, start y and end y
+/// or `None` if they are mostly transparent.
+fn average_color(
+    pixels: &[[u8; 4]],
+    image_width: u32,
+    (start_x, end_x, start_y, end_y): (u32, u32, u32, u32),
+) -> Option<(u8, u8, u8)> {
+    let (mut r, mut g, mut b, mut alpha) = (0, 0, 0, 0);
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            let [pixel_r, pixel_g, pixel_b, pixel_alpha] = pixels[(y * image_width + x) as usize];
+            r += pixel_r as u64 * pixel_alpha as u64;
+            g += pixel_g as u64 * pixel_alpha as u64;
+            b += pixel_b as u64 * pixel_alpha as u64;
+            alpha += pixel_alpha as u64;
+        }
+    }
+
+    let pixel_count = ((end_x - start_x) * (end_y - start_y)) as u64;
+    if alpha == 0 || alpha < ALPHA_THRESHOLD as u64 * pixel_count {
+        return None;
+    }
+    Some(((r / alpha) as u8, (g / alpha) as u8, (b / alpha) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_rgba(width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(data)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_write_png() {
+        let mut canvas = Canvas::new(Size {
+            width: 3,
+            height: 2,
+        });
+        canvas.block(Point { x: 1, y: 1 }, Color::Red);
+        canvas.block(Point { x: 2, y: 2 }, Color::ByteColor(21));
+        let mut bytes = Vec::<u8>::new();
+        write_png(&canvas, &mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        // The whole canvas is exported, including where nothing was drawn
+        assert_eq!((info.width, info.height), (3, 4));
+        #[rustfmt::skip]
+        assert_eq!(
+            data,
+            [
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_png() {
+        let size = Size {
+            width: 80,
+            height: 24,
+        };
+        #[rustfmt::skip]
+        let bytes = encode_rgba(2, 3, &[
+            255, 0, 0, 255, 0, 0, 0, 0,
+            0, 0, 0, 0, 1, 2, 3, 255,
+            250, 250, 250, 255, 0, 0, 0, 0,
+        ]);
+
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        read_png(&mut canvas, &mut bytes.as_slice(), &size, true).unwrap();
+        assert_eq!((canvas.size().width, canvas.size().height), (2, 2));
+        let rgb = |r, g, b| Some(Color::Rgb { r, g, b });
+        assert_eq!(canvas.get_block(Point { x: 0, y: 0 }), rgb(255, 0, 0));
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), None);
+        assert_eq!(canvas.get_block(Point { x: 0, y: 1 }), None);
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), rgb(1, 2, 3));
+        assert_eq!(canvas.get_block(Point { x: 0, y: 2 }), rgb(250, 250, 250));
+
+        read_png(&mut canvas, &mut bytes.as_slice(), &size, false).unwrap();
+        assert_eq!(canvas.get_block(Point { x: 0, y: 0 }), Some(Color::Red));
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), Some(Color::Black));
+        assert_eq!(canvas.get_block(Point { x: 0, y: 2 }), Some(Color::White));
+    }
+
+    #[test]
+    fn test_read_png_scaled_down() {
+        let size = Size {
+            width: 2,
+            height: 1,
+        };
+        #[rustfmt::skip]
+        let bytes = encode_rgba(4, 4, &[
+            200, 0, 0, 255, 100, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0,
+            200, 0, 0, 255, 100, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 200, 255,
+        ]);
+
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        read_png(&mut canvas, &mut bytes.as_slice(), &size, true).unwrap();
+        assert_eq!(
+            canvas.get_block(Point { x: 0, y: 0 }),
+            Some(Color::Rgb { r: 150, g: 0, b: 0 })
+        );
+        assert_eq!(canvas.get_block(Point { x: 1, y: 0 }), None);
+        assert_eq!(canvas.get_block(Point { x: 0, y: 1 }), None);
+        // Only one of the four pixels is opaque
+        assert_eq!(canvas.get_block(Point { x: 1, y: 1 }), None);
+        assert!(!canvas.contains(Point { x: 2, y: 0 }));
+    }
+}
@@ -0,0 +1,419 @@
+//! BlockPaint's own versioned file format.
+//!
+//! All integers are little-endian. A file starts with a header:
+//!
+//! * The magic bytes `BLOCKPAINT`
+//! * The format version (1 byte)
+//! * The canvas width in cells (2 bytes)
+//! * The canvas height in cells (2 bytes)
+//! * The layer count (2 bytes)
+//!
+//! The header is followed by the layers from bottom to top. A layer starts with a byte that is 1 if it's visible
+//! and 0 if it's hidden, followed by `width * height` cells, row by row.
+//! Each cell consists of the upper block's color, the lower block's color and the character.
+//!
+//! Version 1 files have no layer count and consist of a single visible layer without the visibility byte.
+//!
+//! A color starts with a tag byte: 0 for no color, 1 to 16 for the 4-bit colors,
+//! 17 for an 8-bit color followed by its byte and 18 for an RGB color followed by its 3 components.
+//!
+//! A character is a byte that is 0 for no character or 1 for a character followed by its code point (4 bytes).
+
+use crate::{
+    canvas::{layers::Layer, Canvas, Cell, MAX_SIZE},
+    util::{Color, Point, Size},
+};
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8] = b"BLOCKPAINT";
+const VERSION: u8 = 2;
+/// Files with more layers than this are assumed to be corrupt.
+const MAX_LAYER_COUNT: u16 = 256;
+
+const NO_COLOR_TAG: u8 = 0;
+const BYTE_COLOR_TAG: u8 = 17;
+const RGB_COLOR_TAG: u8 = 18;
+
+/// The 4-bit colors in the order of their tags, starting at tag 1.
+const FOUR_BIT_COLORS: [Color; 16] = [
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Black,
+    Color::Gray,
+    Color::DarkGray,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+/// Saves the canvas to a temporary file next to the path first and then replaces the file at the path with it
+/// so that the file is left untouched if saving fails.
+pub fn save(canvas: &Canvas, path: &Path) -> io::Result<()> {
+    layer_count(canvas)?;
+    let mut temporary_file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+        .to_os_string();
+    temporary_file_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_file_name);
+
+    let result = fs::File::create(&temporary_path).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        write(canvas, &mut writer)?;
+        writer.flush()
+    });
+    match result {
+        Ok(()) => fs::rename(&temporary_path, path),
+        Err(error) => {
+            let _ = fs::remove_file(&temporary_path);
+            Err(error)
+        }
+    }
+}
+
+pub fn load(canvas: &mut Canvas, path: &Path) -> io::Result<()> {
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    read(canvas, &mut reader)
+}
+
+pub fn write(canvas: &Canvas, writer: &mut impl Write) -> io::Result<()> {
+    let size = canvas.size();
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(&size.width.to_le_bytes())?;
+    writer.write_all(&size.height.to_le_bytes())?;
+    writer.write_all(&layer_count(canvas)?.to_le_bytes())?;
+
+    for layer in canvas.layers() {
+        writer.write_all(&[layer.visible as u8])?;
+        for cell in &layer.cells {
+            write_color(writer, cell.upper_block)?;
+            write_color(writer, cell.lower_block)?;
+            match cell.character {
+                Some(character) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&(character as u32).to_le_bytes())?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn layer_count(canvas: &Canvas) -> io::Result<u16> {
+    u16::try_from(canvas.layers().len())
+        .ok()
+        .filter(|layer_count| *layer_count <= MAX_LAYER_COUNT)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "too many layers"))
+}
+
+/// Replaces the canvas's layers and size with the ones read and selects the top layer.
+pub fn read(canvas: &mut Canvas, reader: &mut impl Read) -> io::Result<()> {
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(invalid_data("not a BlockPaint file"));
+    }
+    let version = read_u8(reader)?;
+    if version == 0 {
+        return Err(invalid_data("invalid format version"));
+    }
+    if version > VERSION {
+        return Err(invalid_data(
+            "file was saved by a newer version of BlockPaint",
+        ));
+    }
+    let size = Size {
+        width: read_u16(reader)?,
+        height: read_u16(reader)?,
+    };
+    if size.width == 0
+        || size.height == 0
+        || size.width > MAX_SIZE.width
+        || size.height > MAX_SIZE.height
+    {
+        return Err(invalid_data("invalid canvas size"));
+    }
+    let layer_count = if version == 1 { 1 } else { read_u16(reader)? };
+    if layer_count == 0 {
+        return Err(invalid_data("no layers"));
+    }
+    if layer_count > MAX_LAYER_COUNT {
+        return Err(invalid_data("too many layers"));
+    }
+
+    // Read everything first so that the canvas is left untouched if the file is corrupt.
+    // Nothing is allocated up front so that a file can't make it allocate more than what it actually contains.
+    let mut layers = Vec::<Layer>::new();
+    for _ in 0..layer_count {
+        let visible = if version == 1 {
+            true
+        } else {
+            match read_u8(reader)? {
+                0 => false,
+                1 => true,
+                _ => return Err(invalid_data("invalid layer visibility")),
+            }
+        };
+        layers.push(Layer {
+            cells: read_cells(reader, &size)?,
+            visible,
+        });
+    }
+
+    canvas.resize(size);
+    canvas.set_layers(layers);
+    canvas.select_layer(layer_count as usize - 1);
+
+    Ok(())
+}
+
+/// Reads the cells of a layer of the given size row by row.
+fn read_cells(reader: &mut impl Read, size: &Size) -> io::Result<Vec<Cell>> {
+    let mut cells = Vec::<Cell>::new();
+    for row in 0..size.height {
+        for x in 0..size.width {
+            let upper_block = read_color(reader)?;
+            let lower_block = read_color(reader)?;
+            let character = match read_u8(reader)? {
+                0 => None,
+                1 => Some(
+                    char::try_from(read_u32(reader)?)
+                        .map_err(|_| invalid_data("invalid character"))?,
+                ),
+                _ => return Err(invalid_data("invalid character tag")),
+            };
+            let cell = Cell {
+                upper_block,
+                lower_block,
+                character,
+                ..Default::default()
+            };
+            if cell == Cell::default() {
+                cells.push(cell);
+                continue;
+            }
+            let upper_point = Point { x, y: row * 2 };
+            cells.push(Cell {
+                upper_point,
+                lower_point: Point {
+                    y: upper_point.y + 1,
+                    ..upper_point
+                },
+                ..cell
+            });
+        }
+    }
+    Ok(cells)
+}
+
+fn write_color(writer: &mut impl Write, color: Option<Color>) -> io::Result<()> {
+    match color {
+        None => writer.write_all(&[NO_COLOR_TAG]),
+        Some(Color::ByteColor(byte)) => writer.write_all(&[BYTE_COLOR_TAG, byte]),
+        Some(Color::Rgb { r, g, b }) => writer.write_all(&[RGB_COLOR_TAG, r, g, b]),
+        Some(color) => {
+            let index = FOUR_BIT_COLORS
+                .iter()
+                .position(|four_bit_color| *four_bit_color == color)
+                .expect("color is a 4-bit color");
+            writer.write_all(&[index as u8 + 1])
+        }
+    }
+}
+
+fn read_color(reader: &mut impl Read) -> io::Result<Option<Color>> {
+    let color = match read_u8(reader)? {
+        NO_COLOR_TAG => None,
+        BYTE_COLOR_TAG => Some(Color::ByteColor(read_u8(reader)?)),
+        RGB_COLOR_TAG => Some(Color::Rgb {
+            r: read_u8(reader)?,
+            g: read_u8(reader)?,
+            b: read_u8(reader)?,
+        }),
+        tag => Some(
+            *FOUR_BIT_COLORS
+                .get(tag as usize - 1)
+                .ok_or_else(|| invalid_data("invalid color tag"))?,
+        ),
+    };
+    Ok(color)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let size = Size {
+            width: 300,
+            height: 10,
+        };
+        let mut canvas = Canvas::new(size.clone());
+        canvas.set_cell(
+            Point { x: 3, y: 4 },
+            Cell {
+                upper_block: Some(Color::Red),
+                lower_block: Some(Color::ByteColor(200)),
+                ..Default::default()
+            },
+        );
+        canvas.set_cell(
+            Point { x: 0, y: 7 },
+            Cell {
+                lower_block: Some(Color::Rgb { r: 1, g: 2, b: 3 }),
+                ..Default::default()
+            },
+        );
+        canvas.write_character(Point { x: 10, y: 10 }, 'ä');
+        canvas.add_layer();
+        canvas.toggle_layer_visibility();
+        canvas.set_cell(
+            Point { x: 299, y: 19 },
+            Cell {
+                upper_block: Some(Color::Blue),
+                ..Default::default()
+            },
+        );
+
+        let mut bytes = Vec::<u8>::new();
+        write(&canvas, &mut bytes).unwrap();
+
+        let mut loaded_canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        read(&mut loaded_canvas, &mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            (loaded_canvas.size().width, loaded_canvas.size().height),
+            (size.width, size.height)
+        );
+        assert!(loaded_canvas.layers() == canvas.layers());
+        assert_eq!(loaded_canvas.current_layer(), 1);
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let mut canvas = Canvas::new(Size {
+            width: 10,
+            height: 10,
+        });
+        assert!(read(&mut canvas, &mut &b"NOTAPAINTFILE"[..]).is_err());
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\xFF\x01\x00\x01\x00\x00\x00\x00"[..]
+        )
+        .is_err());
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x01\x01\x00\x01\x00\x13\x00\x00"[..]
+        )
+        .is_err());
+        assert!(read(&mut canvas, &mut &b"BLOCKPAINT\x01\x01\x00\x01\x00\x00"[..]).is_err());
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x01\x01\x00\x01\x00\x00\x00\x00"[..]
+        )
+        .is_ok());
+        // No layers
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x02\x01\x00\x01\x00\x00\x00"[..]
+        )
+        .is_err());
+        // Version 0
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x00\x01\x00\x01\x00\x00\x00\x00"[..]
+        )
+        .is_err());
+        // Too large and too many layers to be allocated
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x02\xFF\xFF\xFF\xFF\x01\x00"[..]
+        )
+        .is_err());
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x02\x00\x08\x00\x08\xFF\xFF"[..]
+        )
+        .is_err());
+        assert!(read(
+            &mut canvas,
+            &mut &b"BLOCKPAINT\x02\x01\x00\x01\x00\x02\x00\x00\x00\x00\x00\x01\x00\x00\x00"[..]
+        )
+        .is_ok());
+        assert_eq!(canvas.layers().len(), 2);
+        assert!(!canvas.layers()[0].visible);
+    }
+
+    #[test]
+    fn test_failed_save() {
+        let path = std::env::temp_dir().join(format!("blockpaint-test-{}", std::process::id()));
+        let mut canvas = Canvas::new(Size {
+            width: 2,
+            height: 2,
+        });
+        canvas.block(Point { x: 1, y: 1 }, Color::Red);
+        save(&canvas, &path).unwrap();
+
+        // The saved file is kept as it was
+        for _ in 0..MAX_LAYER_COUNT {
+            canvas.add_layer();
+        }
+        assert!(save(&canvas, &path).is_err());
+        let mut loaded_canvas = Canvas::new(Size {
+            width: 1,
+            height: 1,
+        });
+        load(&mut loaded_canvas, &path).unwrap();
+        assert_eq!(loaded_canvas.layers().len(), 1);
+        assert_eq!(
+            loaded_canvas.get_block(Point { x: 1, y: 1 }),
+            Some(Color::Red)
+        );
+        assert!(!path
+            .with_file_name(format!("blockpaint-test-{}.tmp", std::process::id()))
+            .exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
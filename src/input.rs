@@ -54,12 +54,6 @@ impl Field {
         self.x_center = Self::x_center(self.point.x, self.input.len());
     }
 
-    pub fn clear(&mut self) {
-        self.input.clear();
-        self.cursor_x = 0;
-        self.update();
-    }
-
     pub fn remove_word_to_left_of_cursor(&mut self) {
         if let Some(space_index) = self
             .input
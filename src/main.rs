@@ -1,17 +1,20 @@
 mod canvas;
 mod debug;
 mod event;
+mod file;
 mod input;
 mod palette;
 mod terminal;
 mod util;
 
+use std::{env, path::PathBuf};
+
 fn main() {
-    let mut terminal = terminal::Terminal::new();
+    let path = env::args_os().nth(1).map(PathBuf::from);
 
-    terminal.set_title("BlockPaint (Untitled)");
+    let mut terminal = terminal::Terminal::new();
 
     terminal.initialize();
-    event::main_loop(&mut terminal);
+    event::main_loop(&mut terminal, path);
     terminal.deinitialize();
 }
@@ -8,6 +8,6 @@ pub const SIZE: Size = Size {
     width: 26,
     height: 12,
 };
-pub const GRAYSCALE_COLOR_COUNT: SIZE = 24;
-pub const INPUT_FIELD_WIDTH: SIZE = GRAYSCALE_COLOR_COUNT;
-pub const FOUR_BIT_COLOR_COUNT: SIZE = 8 * 2;
+pub const GRAYSCALE_COLOR_COUNT: u8 = 24;
+pub const INPUT_FIELD_WIDTH: SIZE = GRAYSCALE_COLOR_COUNT as SIZE;
+pub const FOUR_BIT_COLOR_COUNT: u8 = 8 * 2;
@@ -139,7 +139,7 @@ pub fn draw(
         .enumerate();
 
     for (index, color) in colors {
-        if index as SIZE % palette::SIZE.width == 0 {
+        if (index as SIZE) % palette::SIZE.width == 0 {
             if index > 0 {
                 point.x -= palette::SIZE.width;
             }
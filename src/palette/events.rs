@@ -10,7 +10,7 @@ use crate::{
 
 pub fn handle(
     terminal: &mut Terminal,
-    clickable_colors: &mut Vec<colors::ClickableColor>,
+    clickable_colors: &[colors::ClickableColor],
     state: &mut crate::event::State,
     input_field: &mut input::Field,
     palette_input_field_clickable_colors: &mut Vec<colors::ClickableColor>,
@@ -91,12 +91,10 @@ pub fn handle(
                     _ => {}
                 }
             }
-            Event::Key(key) => match key {
-                KeyEvent::Tab => {
-                    return;
-                }
-                _ => {}
-            },
+            Event::Key(KeyEvent::Tab) => {
+                return;
+            }
+            Event::Key(_) => {}
             Event::Resize(_) => {}
         }
     }
@@ -1,13 +1,14 @@
 use crate::util::{Point, Size};
 use std::{
-    fmt,
+    env, fmt,
     io::{self, Write},
 };
 pub mod event;
 mod sys;
 
-/// Defines the terminal width and height boundary. 255 cells.
-pub type SIZE = u8;
+/// Defines the terminal width and height boundary. 65535 cells.
+#[allow(clippy::upper_case_acronyms)]
+pub type SIZE = u16;
 
 #[cfg(not(debug_assertions))]
 pub struct Terminal {
@@ -68,10 +69,18 @@ impl Terminal {
         self.handle.flush().expect("flushing failed");
     }
 
+    /// Returns whether the terminal advertises support for 24-bit colors.
+    pub fn supports_truecolor() -> bool {
+        matches!(
+            env::var("COLORTERM").as_deref(),
+            Ok("truecolor") | Ok("24bit")
+        )
+    }
+
     pub fn get_centered_border_point(&self, size: &Size) -> Point {
         Point {
-            x: self.size.width / 2 - size.width / 2,
-            y: self.size.height / 2 - size.height / 2,
+            x: (self.size.width / 2).saturating_sub(size.width / 2),
+            y: (self.size.height / 2).saturating_sub(size.height / 2),
         }
     }
 }
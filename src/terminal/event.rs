@@ -35,8 +35,12 @@ pub enum KeyEvent {
     Right(Option<KeyModifier>),
     Char(char, Option<KeyModifier>),
     Tab,
+    Enter,
+    PageUp,
+    PageDown,
     Esc,
     Backspace(Option<KeyModifier>),
+    Delete,
 }
 
 pub enum Event {
@@ -122,8 +122,12 @@ impl Terminal {
                         Event::Key(KeyEvent::Char(key, None))
                     }
                 }
+                event::KeyCode::Enter => Event::Key(KeyEvent::Enter),
+                event::KeyCode::PageUp => Event::Key(KeyEvent::PageUp),
+                event::KeyCode::PageDown => Event::Key(KeyEvent::PageDown),
                 event::KeyCode::Esc => Event::Key(KeyEvent::Esc),
                 event::KeyCode::Backspace => Event::Key(KeyEvent::Backspace(None)),
+                event::KeyCode::Delete => Event::Key(KeyEvent::Delete),
                 event::KeyCode::Left if modifiers == event::KeyModifiers::CONTROL => {
                     Event::Key(KeyEvent::Left(Some(KeyModifier::Control)))
                 }
@@ -144,16 +148,14 @@ impl Terminal {
     }
 
     pub fn set_cursor(&mut self, point: Point) {
-        self.handle
-            .queue(cursor::MoveTo(point.x as u16, point.y as u16))
-            .unwrap();
+        self.handle.queue(cursor::MoveTo(point.x, point.y)).unwrap();
     }
 
     pub fn move_cursor_left(&mut self, cells: SIZE) {
-        self.handle.queue(cursor::MoveLeft(cells as u16)).unwrap();
+        self.handle.queue(cursor::MoveLeft(cells)).unwrap();
     }
     pub fn move_cursor_right(&mut self, cells: SIZE) {
-        self.handle.queue(cursor::MoveRight(cells as u16)).unwrap();
+        self.handle.queue(cursor::MoveRight(cells)).unwrap();
     }
 
     pub fn save_cursor_position(&mut self) {
@@ -170,20 +172,27 @@ impl Terminal {
             .unwrap();
     }
 
-    pub fn enable_italic(&mut self) {
-        self.write_args(format_args!("{}", style::Attribute::Italic));
-    }
-
-    pub fn disable_italic(&mut self) {
-        self.write_args(format_args!("{}", style::Attribute::NoItalic));
-    }
-
     pub fn set_background_color(&mut self, color: Color) {
         self.handle
             .queue(style::SetBackgroundColor(Self::convert_color(color)))
             .unwrap();
     }
 
+    /// Returns the escape sequence written by [`Terminal::set_foreground_color`].
+    pub fn foreground_color_sequence(color: Color) -> String {
+        style::SetForegroundColor(Self::convert_color(color)).to_string()
+    }
+
+    /// Returns the escape sequence written by [`Terminal::set_background_color`].
+    pub fn background_color_sequence(color: Color) -> String {
+        style::SetBackgroundColor(Self::convert_color(color)).to_string()
+    }
+
+    /// Returns the escape sequence written by [`Terminal::reset_colors`].
+    pub fn reset_colors_sequence() -> String {
+        style::ResetColor.to_string()
+    }
+
     fn convert_color(color: Color) -> style::Color {
         match color {
             Color::Black => style::Color::Black,
@@ -1,5 +1,5 @@
 use crate::{palette, terminal::SIZE};
-use std::{convert::TryFrom, fmt, ops};
+use std::{convert::TryFrom, fmt};
 
 #[derive(Clone, Debug, Copy, PartialEq, Default)]
 pub struct Point {
@@ -12,7 +12,7 @@ impl fmt::Display for Point {
         write!(f, "({}, {})", self.x, self.y)
     }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Size {
     pub width: SIZE,
     pub height: SIZE,
@@ -21,21 +21,14 @@ pub struct Size {
 impl Size {
     pub fn from_terminal_size(width: usize, height: usize) -> Self {
         Self {
-            width: width.clamp(0, 255) as u8,
-            height: height.clamp(0, 255) as u8,
+            width: width.clamp(0, SIZE::MAX as usize) as SIZE,
+            height: height.clamp(0, SIZE::MAX as usize) as SIZE,
         }
     }
 }
 
-struct Range(ops::Range<SIZE>);
-
-impl fmt::Display for Range {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{} to {}", self.0.start, self.0.end)
-    }
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[allow(clippy::enum_variant_names)]
 pub enum Color {
     // 4-bit colors
     DarkRed,
@@ -87,6 +80,131 @@ impl Color {
             _ => Black,
         }
     }
+
+    /// Returns the ANSI color index (0 to 15) of a 4-bit color.
+    pub fn four_bit_index(&self) -> Option<u8> {
+        use Color::*;
+        let index = match self {
+            Black => 0,
+            DarkRed => 1,
+            DarkGreen => 2,
+            DarkYellow => 3,
+            DarkBlue => 4,
+            DarkMagenta => 5,
+            DarkCyan => 6,
+            Gray => 7,
+            DarkGray => 8,
+            Red => 9,
+            Green => 10,
+            Yellow => 11,
+            Blue => 12,
+            Magenta => 13,
+            Cyan => 14,
+            White => 15,
+            ByteColor(_) | Rgb { .. } => return None,
+        };
+        Some(index)
+    }
+
+    /// Returns the color of the given xterm 256-color table index, using the 4-bit colors for the first 16 indices.
+    pub fn from_ansi_index(index: u8) -> Self {
+        use Color::*;
+        match index {
+            0 => Black,
+            1 => DarkRed,
+            2 => DarkGreen,
+            3 => DarkYellow,
+            4 => DarkBlue,
+            5 => DarkMagenta,
+            6 => DarkCyan,
+            7 => Gray,
+            8 => DarkGray,
+            9 => Red,
+            10 => Green,
+            11 => Yellow,
+            12 => Blue,
+            13 => Magenta,
+            14 => Cyan,
+            15 => White,
+            _ => ByteColor(index),
+        }
+    }
+
+    /// Returns the color of the xterm 256-color table that is closest to the given RGB color.
+    pub fn nearest_ansi_color(r: u8, g: u8, b: u8) -> Self {
+        Self::nearest_color_of(0..=u8::MAX, r, g, b)
+    }
+
+    /// Returns the 4-bit color that is closest to the given RGB color.
+    pub fn nearest_four_bit_color(r: u8, g: u8, b: u8) -> Self {
+        Self::nearest_color_of(0..palette::FOUR_BIT_COLOR_COUNT, r, g, b)
+    }
+
+    fn nearest_color_of(indices: impl Iterator<Item = u8>, r: u8, g: u8, b: u8) -> Self {
+        let distance = |index: &u8| {
+            let (other_r, other_g, other_b) = byte_color_to_rgb(*index);
+            (r as i32 - other_r as i32).pow(2)
+                + (g as i32 - other_g as i32).pow(2)
+                + (b as i32 - other_b as i32).pow(2)
+        };
+        let index = indices
+            .min_by_key(distance)
+            .expect("there are colors to compare");
+        Self::from_ansi_index(index)
+    }
+
+    /// Converts the color into its red, green and blue components as defined by the xterm 256-color table.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb { r, g, b } => (r, g, b),
+            Color::ByteColor(byte) => byte_color_to_rgb(byte),
+            color => byte_color_to_rgb(color.four_bit_index().expect("color is a 4-bit color")),
+        }
+    }
+}
+
+/// The 16 system colors of the xterm 256-color table.
+const SYSTEM_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (128, 0, 0),
+    (0, 128, 0),
+    (128, 128, 0),
+    (0, 0, 128),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (128, 128, 128),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (0, 0, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each component of the 6×6×6 color cube of the xterm 256-color table.
+const COLOR_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Converts an xterm 256-color table index into its red, green and blue components.
+///
+/// See https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit for more information.
+pub fn byte_color_to_rgb(byte: u8) -> (u8, u8, u8) {
+    match byte {
+        0..=15 => SYSTEM_COLORS[byte as usize],
+        16..=231 => {
+            let index = byte - 16;
+            (
+                COLOR_CUBE_LEVELS[(index / 36) as usize],
+                COLOR_CUBE_LEVELS[(index / 6 % 6) as usize],
+                COLOR_CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        232..=255 => {
+            let level = 8 + (byte - 232) * 10;
+            (level, level, level)
+        }
+    }
 }
 
 /// Tries to parse the input into an RGB color.
@@ -158,6 +276,53 @@ pub fn parse_rgb_color(string: &str) -> Option<Color> {
     }
 }
 
+/// Tries to parse the input into a size given as width and height, e.g. 80x24.
+pub fn parse_size(string: &str) -> Option<Size> {
+    let mut numbers = string
+        .split(|char: char| !char.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<usize>().unwrap_or(usize::MAX));
+    let size = Size::from_terminal_size(numbers.next()?, numbers.next()?);
+    if numbers.next().is_some() {
+        return None;
+    }
+    Some(size)
+}
+
+/// A small pseudorandom number generator (SplitMix64). The same seed always gives the same numbers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded with the current time.
+    pub fn from_time() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(seed)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut number = self.state;
+        number = (number ^ (number >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        number = (number ^ (number >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        number ^ (number >> 31)
+    }
+
+    /// Returns a number from 0 up to but not including the bound.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
 fn parse_hex(string: &str, index: usize) -> Option<Color> {
     if let (Some(r), Some(g), Some(b)) = (
         &string.get(index..index + 2),
@@ -211,4 +376,48 @@ mod tests {
         // assert_eq!(parse("255,255,255555555"), rgb(255, 255, 255));
         // assert_eq!(parse("255,255,255efefef"), rgb(255, 255, 255));
     }
+
+    #[test]
+    fn test_parse_size() {
+        let parse = |string| parse_size(string).map(|size| (size.width, size.height));
+        assert_eq!(parse("80x24"), Some((80, 24)));
+        assert_eq!(parse(" 100 , 50 "), Some((100, 50)));
+        assert_eq!(parse("1000x99999999999999999999999"), Some((1000, 65535)));
+        assert_eq!(parse("80"), None);
+        assert_eq!(parse("1x2x3"), None);
+        assert_eq!(parse("x"), None);
+    }
+
+    #[test]
+    fn test_to_rgb() {
+        assert_eq!(Color::Black.to_rgb(), (0, 0, 0));
+        assert_eq!(Color::DarkRed.to_rgb(), (128, 0, 0));
+        assert_eq!(Color::White.to_rgb(), (255, 255, 255));
+        assert_eq!(Color::ByteColor(9).to_rgb(), Color::Red.to_rgb());
+        assert_eq!(Color::ByteColor(16).to_rgb(), (0, 0, 0));
+        assert_eq!(Color::ByteColor(196).to_rgb(), (255, 0, 0));
+        assert_eq!(Color::ByteColor(110).to_rgb(), (135, 175, 215));
+        assert_eq!(Color::ByteColor(231).to_rgb(), (255, 255, 255));
+        assert_eq!(Color::ByteColor(232).to_rgb(), (8, 8, 8));
+        assert_eq!(Color::ByteColor(255).to_rgb(), (238, 238, 238));
+        assert_eq!(Color::Rgb { r: 1, g: 2, b: 3 }.to_rgb(), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_nearest_ansi_color() {
+        assert_eq!(Color::nearest_ansi_color(0, 0, 0), Color::Black);
+        assert_eq!(Color::nearest_ansi_color(250, 2, 3), Color::Red);
+        assert_eq!(
+            Color::nearest_ansi_color(130, 170, 220),
+            Color::ByteColor(110)
+        );
+        assert_eq!(
+            Color::nearest_ansi_color(100, 100, 100),
+            Color::ByteColor(241)
+        );
+        for index in 0..=u8::MAX {
+            let (r, g, b) = Color::from_ansi_index(index).to_rgb();
+            assert_eq!(Color::nearest_ansi_color(r, g, b).to_rgb(), (r, g, b));
+        }
+    }
 }